@@ -4,11 +4,12 @@ use axum::{response::Json, routing::post, Router};
 
 // This agent uses the gameplay crate types. This is currently only
 // easy for rust but we can add more helper libs for other languages.
-use gameplay::games::connect4::{Action, Connect4};
+use gameplay::games::connect4::{Action, Bitboard};
 
 mod mcts;
 
-async fn agent(Json(game_state): Json<Connect4>) -> Json<Action> {
+// Bitboard reads the same json as Connect4 but plays out games much faster.
+async fn agent(Json(game_state): Json<Bitboard>) -> Json<Action> {
     let action = mcts::agent(&game_state);
     Json(action)
 }
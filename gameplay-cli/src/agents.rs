@@ -0,0 +1,120 @@
+//! Choosing agents on the command line.
+
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+
+use clap::Args;
+use reqwest::{Client, Url};
+
+use gameplay::agents::{Builtin, BuiltinGame};
+use gameplay::runner::{HttpAgent, Local, Player, SubprocessAgent};
+
+/// An agent given on the command line. Either the url of an agent service,
+/// `cmd:` and a command that runs the agent as a subprocess or `builtin:`
+/// and one of the agents built into this binary, like
+/// `builtin:minimax?depth=4`.
+#[derive(Clone, Debug)]
+pub enum AgentSpec {
+    Http(Url),
+    Command(String),
+    Builtin(Builtin),
+}
+
+impl AgentSpec {
+    /// The player for this agent, fails if it's a builtin that can't play
+    /// `S`.
+    pub fn player<S: BuiltinGame>(&self, client: &Client) -> io::Result<Box<dyn Player<S>>> {
+        Ok(match self {
+            AgentSpec::Http(url) => Box::new(HttpAgent::new(client.clone(), url.clone())),
+            AgentSpec::Command(command) => {
+                // Checked to have a program when parsed.
+                let mut words = command.split_whitespace().map(str::to_string);
+                let program = words.next().unwrap();
+                Box::new(SubprocessAgent::new(program, words.collect()))
+            }
+            AgentSpec::Builtin(builtin) => {
+                let mut agent = builtin
+                    .agent::<S>()
+                    .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+                Box::new(Local::new(self.to_string(), move |state: &S| {
+                    agent.choose(state)
+                }))
+            }
+        })
+    }
+}
+
+impl FromStr for AgentSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(cmd) = s.strip_prefix("cmd:") {
+            return command(cmd).map(AgentSpec::Command);
+        }
+        if let Some(builtin) = s.strip_prefix("builtin:") {
+            return builtin.parse().map(AgentSpec::Builtin);
+        }
+        Url::parse(s)
+            .map(AgentSpec::Http)
+            .map_err(|err| format!("{}: {}", s, err))
+    }
+}
+
+impl fmt::Display for AgentSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AgentSpec::Http(url) => write!(f, "{}", url),
+            AgentSpec::Command(command) => write!(f, "{}", command),
+            AgentSpec::Builtin(builtin) => write!(f, "builtin:{}", builtin),
+        }
+    }
+}
+
+fn command(s: &str) -> Result<String, String> {
+    match s.trim() {
+        "" => Err("Empty command".to_string()),
+        command => Ok(command.to_string()),
+    }
+}
+
+/// The agents playing each side of a match. Each side is given by one of
+/// its flags.
+#[derive(Args)]
+pub struct PlayerArgs {
+    /// Agent playing as player 0, a url, `cmd:` and a command or `builtin:` and a builtin agent
+    #[arg(long, group = "player0_agent")]
+    player0: Option<AgentSpec>,
+    #[arg(long, group = "player0_agent")]
+    player0_url: Option<Url>,
+    /// Run the player 0 agent as a subprocess that talks json over stdin/stdout
+    #[arg(long, group = "player0_agent", value_parser = command)]
+    player0_cmd: Option<String>,
+    /// Agent playing as player 1, a url, `cmd:` and a command or `builtin:` and a builtin agent
+    #[arg(long, group = "player1_agent")]
+    player1: Option<AgentSpec>,
+    #[arg(long, group = "player1_agent")]
+    player1_url: Option<Url>,
+    /// Run the player 1 agent as a subprocess that talks json over stdin/stdout
+    #[arg(long, group = "player1_agent", value_parser = command)]
+    player1_cmd: Option<String>,
+}
+
+impl PlayerArgs {
+    /// Every way to give the sides their agents, for when one is missing.
+    pub const USAGE: &'static str = "give --player0 and --player1 a url, `cmd:` and a \
+        command or `builtin:` and a builtin agent like `builtin:minimax?depth=4`, or use \
+        --player0-url or --player0-cmd and --player1-url or --player1-cmd";
+
+    /// The agent for each side, `None` if it wasn't given.
+    pub fn agents(self) -> [Option<AgentSpec>; 2] {
+        let spec = |spec: Option<AgentSpec>, url: Option<Url>, command: Option<String>| {
+            spec.or(url.map(AgentSpec::Http))
+                .or(command.map(AgentSpec::Command))
+        };
+        [
+            spec(self.player0, self.player0_url, self.player0_cmd),
+            spec(self.player1, self.player1_url, self.player1_cmd),
+        ]
+    }
+}
@@ -0,0 +1,220 @@
+//! Evaluate every move in a Connect4 position.
+//!
+//! Each column is solved if it can be within the time limit, otherwise it
+//! gets a negamax search with a simple evaluation instead.
+
+use std::io;
+use std::time::{Duration, Instant};
+
+use gameplay::games::connect4::solver::{self, Outcome, Solver, Value};
+use gameplay::games::connect4::{Action, Bitboard, Connect4, Error, Moves, COLS, ROWS};
+use gameplay::games::{GameState, GameStatus};
+use gameplay::search::{Negamax, MAX_PLY, WIN};
+
+use crate::tui::TuiGame;
+
+/// How many lines of four go through each cell, by column then row.
+const LINES: [[i32; ROWS]; COLS] = [
+    [3, 4, 5, 5, 4, 3],
+    [4, 6, 8, 8, 6, 4],
+    [5, 8, 11, 11, 8, 5],
+    [7, 10, 13, 13, 10, 7],
+    [5, 8, 11, 11, 8, 5],
+    [4, 6, 8, 8, 6, 4],
+    [3, 4, 5, 5, 4, 3],
+];
+
+/// The evaluation of a move.
+enum Evaluation {
+    Solved(Value),
+    /// Searched `depth` moves ahead but not solved, the value is the
+    /// search's for the player who moved.
+    Searched {
+        value: i32,
+        depth: usize,
+    },
+}
+
+/// Read a position, either the columns played from the start counting from
+/// 1 (like `4453`), a diagram or a board as json.
+pub fn parse_position(position: &str) -> io::Result<Bitboard> {
+    let invalid = |err| io::Error::new(io::ErrorKind::InvalidInput, err);
+    if position.trim().starts_with('{') {
+        serde_json::from_str(position).map_err(|err| invalid(err.to_string()))
+    } else {
+        position
+            .parse()
+            .map_err(|err: Error| invalid(err.to_string()))
+    }
+}
+
+/// Print the evaluation and best line of every legal move in `state`,
+/// spending up to `time` on each.
+pub fn analyze(state: &Bitboard, time: Duration) -> io::Result<()> {
+    let GameStatus::InProgress { next_player } = state.status() else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "the game is already over",
+        ));
+    };
+    println!(
+        "{} to move",
+        <Connect4 as TuiGame>::player_name(next_player)
+    );
+    println!("{:>6}  {:<7}  {:<20}  line", "column", "result", "value");
+
+    let mut solver = Solver::new();
+    for action in state.legal_actions() {
+        let mut next = state.clone();
+        next.apply_action(&action).unwrap();
+        let deadline = Instant::now() + time;
+        let (evaluation, rest) = if let GameStatus::Over { result } = next.status() {
+            // Playing can't forfeit, so the player who moved won or tied.
+            let outcome = match result.winner() {
+                Some(_) => Outcome::Win,
+                None => Outcome::Draw,
+            };
+            (Evaluation::Solved(Value { outcome, moves: 1 }), vec![])
+        } else if let Some(value) = solver.solve_within(&next, time) {
+            (
+                Evaluation::Solved(before(value)),
+                solved_line(&mut solver, &next, deadline),
+            )
+        } else {
+            searched(&next, time)
+        };
+        let (result, value) = match evaluation {
+            Evaluation::Solved(value) => (outcome(value.outcome), value.to_string()),
+            Evaluation::Searched { value, depth } => match forced(value) {
+                Some(value) => (outcome(value.outcome), value.to_string()),
+                None => ("?", format!("{:+} at depth {}", value, depth)),
+            },
+        };
+        let line = Moves(std::iter::once(action).chain(rest).collect());
+        println!(
+            "{:>6}  {:<7}  {:<20}  {}",
+            action.column + 1,
+            result,
+            value,
+            line
+        );
+    }
+    Ok(())
+}
+
+fn outcome(outcome: Outcome) -> &'static str {
+    match outcome {
+        Outcome::Win => "win",
+        Outcome::Draw => "draw",
+        Outcome::Loss => "loss",
+    }
+}
+
+/// The value of a move for the player who made it, from the `value` of the
+/// position it leads to.
+fn before(value: Value) -> Value {
+    let outcome = match value.outcome {
+        Outcome::Win => Outcome::Loss,
+        Outcome::Draw => Outcome::Draw,
+        Outcome::Loss => Outcome::Win,
+    };
+    Value {
+        outcome,
+        moves: value.moves + 1,
+    }
+}
+
+/// The best play from a solved `state`, for as long as each position can be
+/// solved before `deadline`.
+fn solved_line(solver: &mut Solver, state: &Bitboard, deadline: Instant) -> Vec<Action> {
+    let mut state = state.clone();
+    let mut line = vec![];
+    while let GameStatus::InProgress { .. } = state.status() {
+        let time = deadline.saturating_duration_since(Instant::now());
+        let Some(values) = solver.analyze_within(&state, time) else {
+            break;
+        };
+        let action = solver::best(&values);
+        state.apply_action(&action).unwrap();
+        line.push(action);
+    }
+    line
+}
+
+/// Search `state` when it can't be solved in time.
+fn searched(state: &Bitboard, time: Duration) -> (Evaluation, Vec<Action>) {
+    let mut search = Negamax::new(evaluate, ROWS * COLS).time_limit(time);
+    let searched = search.search(state);
+    let evaluation = Evaluation::Searched {
+        value: -searched.value,
+        depth: searched.depth,
+    };
+    // Past the searched depth the table only has guesses.
+    let mut line = search.line(state);
+    line.truncate(searched.depth);
+    (evaluation, line)
+}
+
+/// A searched value for the player who moved as a solved one, if it's a
+/// forced result.
+fn forced(value: i32) -> Option<Value> {
+    let forced = WIN - MAX_PLY as i32;
+    // The search counts from the position after the move.
+    let moves = (WIN - value.abs()) as usize + 1;
+    match value {
+        v if v > forced => Some(Value {
+            outcome: Outcome::Win,
+            moves,
+        }),
+        v if v < -forced => Some(Value {
+            outcome: Outcome::Loss,
+            moves,
+        }),
+        _ => None,
+    }
+}
+
+/// Lines of four through the player to move's chips less those through
+/// their opponent's.
+fn evaluate(state: &Bitboard) -> i32 {
+    let GameStatus::InProgress { next_player: me } = state.status() else {
+        unreachable!("only games in progress are evaluated")
+    };
+    let mut value = 0;
+    for (col, lines) in LINES.iter().enumerate() {
+        for (row, lines) in lines.iter().enumerate() {
+            match state.get(col, row) {
+                Some(player) if player == me => value += lines,
+                Some(_) => value -= lines,
+                None => {}
+            }
+        }
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn positions_parse_in_every_format() {
+        let moves = parse_position("4453").unwrap();
+        assert_eq!(parse_position(&moves.to_string()).unwrap(), moves);
+        let json = serde_json::to_string(&moves).unwrap();
+        assert_eq!(parse_position(&json).unwrap(), moves);
+
+        assert!(parse_position("4483").is_err());
+        assert!(parse_position("{\"board\": []}").is_err());
+    }
+
+    #[test]
+    fn forced_values_count_the_move() {
+        // The opponent wins 3 moves after this one.
+        let value = forced(-(WIN - 3)).unwrap();
+        assert_eq!(value.outcome, Outcome::Loss);
+        assert_eq!(value.moves, 4);
+        assert_eq!(forced(WIN - 1).unwrap().outcome, Outcome::Win);
+        assert!(forced(25).is_none());
+    }
+}
@@ -0,0 +1,122 @@
+//! Play a batch of headless matches between two agents and summarize them.
+
+use std::io;
+use std::path::Path;
+
+use reqwest::Client;
+
+use gameplay::agents::BuiltinGame;
+use gameplay::games::GameResult;
+use gameplay::ratings::Outcome;
+use gameplay::runner::TimeControl;
+
+use crate::agents::AgentSpec;
+use crate::{headless, leaderboard, records};
+
+#[derive(Default)]
+struct AgentStats {
+    wins: usize,
+    losses: usize,
+    ties: usize,
+    forfeits: usize,
+}
+
+/// Play `games` matches between `a` and `b`, alternating who goes first,
+/// then print the results. Records of the matches are saved in `save`.
+pub async fn bench<S: BuiltinGame>(
+    a: &AgentSpec,
+    b: &AgentSpec,
+    games: usize,
+    time: TimeControl,
+    save: Option<&Path>,
+) -> io::Result<()> {
+    let client = Client::new();
+    let agents = [a, b];
+    let mut stats = [AgentStats::default(), AgentStats::default()];
+    let mut forfeited = 0;
+    let mut total_moves = 0;
+    let mut outcomes = vec![];
+
+    for game in 0..games {
+        // `first` is the agent (0 for a, 1 for b) playing as player 0.
+        let first = game % 2;
+        let order = [first, 1 - first];
+        let outcome =
+            headless::play_match::<S>(&client, [agents[order[0]], agents[order[1]]], time).await?;
+        outcomes.push(Outcome {
+            players: order,
+            result: outcome.result,
+        });
+        if let Some(dir) = save {
+            records::save_in_dir(dir, &outcome.record)?;
+        }
+        let moves = outcome.record.moves.len();
+
+        let summary = match outcome.result {
+            GameResult::Winner { winning_player, .. } => {
+                let winner = order[winning_player];
+                stats[winner].wins += 1;
+                stats[1 - winner].losses += 1;
+                total_moves += moves;
+                format!("{} won as player {}", agents[winner], winning_player)
+            }
+            GameResult::Tie => {
+                stats[0].ties += 1;
+                stats[1].ties += 1;
+                total_moves += moves;
+                "tie".to_string()
+            }
+            GameResult::Forfeit {
+                faulted_player,
+                fault,
+                winning_player,
+            } => {
+                let winner = order[winning_player];
+                let loser = order[faulted_player];
+                stats[winner].wins += 1;
+                stats[loser].losses += 1;
+                stats[loser].forfeits += 1;
+                forfeited += 1;
+                format!(
+                    "{} won as player {}, {} forfeited ({}): {}",
+                    agents[winner],
+                    winning_player,
+                    agents[loser],
+                    fault,
+                    outcome.record.error.as_deref().unwrap_or_default()
+                )
+            }
+        };
+        eprintln!("game {}/{}: {} ({} moves)", game + 1, games, summary, moves);
+    }
+
+    let names = [a.to_string(), b.to_string()];
+    let width = names
+        .iter()
+        .map(|name| name.len())
+        .max()
+        .unwrap_or(0)
+        .max("agent".len());
+    println!(
+        "{:width$}  {:>6}  {:>6}  {:>6}  {:>8}",
+        "agent", "wins", "losses", "ties", "forfeits"
+    );
+    for (name, stats) in names.iter().zip(&stats) {
+        println!(
+            "{:width$}  {:>6}  {:>6}  {:>6}  {:>8}",
+            name, stats.wins, stats.losses, stats.ties, stats.forfeits
+        );
+    }
+    println!("games: {}, forfeited: {}", games, forfeited);
+    // Forfeited matches are cut short, leave them out of the average.
+    let finished = games - forfeited;
+    if finished > 0 {
+        println!(
+            "average length: {:.1} moves",
+            total_moves as f64 / finished as f64
+        );
+    }
+    println!();
+    leaderboard::print(&[&names[0], &names[1]], &outcomes);
+    Ok(())
+}
@@ -0,0 +1,374 @@
+//! Conformance suite for connect4 agents.
+//!
+//! Drives an agent through scripted positions over the same HTTP protocol
+//! that `play` uses and reports which checks pass. Meant to catch protocol
+//! bugs during development without having to play full matches.
+
+use std::fmt::Write;
+
+use reqwest::{Client, Request, Url};
+use uuid::Uuid;
+
+use gameplay::games::connect4::{Action, Connect4, COLS, ROWS};
+use gameplay::games::{Game, GameState, GameStatus};
+use gameplay::runner::http;
+
+/// A request sent to the agent and what came back, printed when a check fails.
+struct Exchange {
+    request: String,
+    response: String,
+}
+
+enum Outcome {
+    Pass,
+    Fail { reason: String, exchange: Exchange },
+}
+
+struct Check {
+    name: &'static str,
+    outcome: Outcome,
+}
+
+/// What a check expects the agent to do with a position.
+enum Expect {
+    /// Any valid action.
+    Valid,
+    /// Exactly this column.
+    Column(usize),
+}
+
+/// A board with every column full except `open`, which has `open_height`
+/// chips in it. The chips are laid out in alternating pairs, offset by a row
+/// in every other column, so nobody has four in a row and both players have
+/// played as many chips as they would have in a real game.
+fn near_full(open: usize, open_height: usize) -> Connect4 {
+    let mut board = vec![None; ROWS * COLS];
+    let mut chips = 0;
+    for col in 0..COLS {
+        let height = if col == open { open_height } else { ROWS };
+        for row in 0..height {
+            board[col * ROWS + row] = Some((col + row.div_ceil(2)) % 2);
+            chips += 1;
+        }
+    }
+    let state: Connect4 = serde_json::from_value(serde_json::json!({
+        "board": board,
+        "next_player": chips % 2,
+    }))
+    .unwrap();
+    debug_assert!(matches!(state.status(), GameStatus::InProgress { .. }));
+    state
+}
+
+fn describe_request(request: &Request) -> String {
+    let mut out = format!("{} {}\n", request.method(), request.url());
+    for (name, value) in request.headers() {
+        let _ = writeln!(out, "{}: {}", name, value.to_str().unwrap_or("<binary>"));
+    }
+    if let Some(body) = request.body().and_then(|body| body.as_bytes()) {
+        out.push_str(&String::from_utf8_lossy(body));
+    }
+    out
+}
+
+/// Send one request to the agent. Returns the response body on a 2xx
+/// response, otherwise fails with the reason.
+async fn send(
+    client: &Client,
+    url: &Url,
+    match_id: &Uuid,
+    player: usize,
+    match_status: &str,
+    state: &Connect4,
+) -> (Result<String, String>, Exchange) {
+    let request = match http::request(
+        client,
+        url,
+        &match_id.to_string(),
+        player,
+        match_status,
+        state,
+    )
+    .build()
+    {
+        Ok(request) => request,
+        Err(err) => {
+            let exchange = Exchange {
+                request: String::new(),
+                response: String::new(),
+            };
+            return (Err(err.to_string()), exchange);
+        }
+    };
+    let mut exchange = Exchange {
+        request: describe_request(&request),
+        response: String::new(),
+    };
+    let resp = match client.execute(request).await {
+        Ok(resp) => resp,
+        Err(err) => return (Err(err.to_string()), exchange),
+    };
+    let status = resp.status();
+    let body = match resp.text().await {
+        Ok(body) => body,
+        Err(err) => return (Err(err.to_string()), exchange),
+    };
+    exchange.response = format!("{}\n{}", status, body);
+    if status.is_success() {
+        (Ok(body), exchange)
+    } else {
+        (Err(format!("agent responded with {}", status)), exchange)
+    }
+}
+
+/// Ask the agent for an action and check it against what we expect.
+async fn check_action(
+    client: &Client,
+    url: &Url,
+    match_id: &Uuid,
+    state: &Connect4,
+    expect: Expect,
+) -> Outcome {
+    let player = match state.status() {
+        GameStatus::InProgress { next_player } => next_player,
+        GameStatus::Over { .. } => unreachable!("scripted position is over"),
+    };
+    let (body, exchange) = send(client, url, match_id, player, "InProgress", state).await;
+    let body = match body {
+        Ok(body) => body,
+        Err(reason) => return Outcome::Fail { reason, exchange },
+    };
+    let action = match serde_json::from_str::<Action>(&body) {
+        Ok(action) => action,
+        Err(err) => {
+            return Outcome::Fail {
+                reason: format!("response is not an action: {}", err),
+                exchange,
+            }
+        }
+    };
+    if !state.valid_action(&action) {
+        return Outcome::Fail {
+            reason: format!("column {} is not a valid action", action.column),
+            exchange,
+        };
+    }
+    match expect {
+        Expect::Column(column) if action.column != column => Outcome::Fail {
+            reason: format!("expected column {}, got column {}", column, action.column),
+            exchange,
+        },
+        _ => Outcome::Pass,
+    }
+}
+
+/// Send the final `Over` notification and check the agent accepts it.
+async fn check_over(client: &Client, url: &Url, match_id: &Uuid, player: usize) -> Outcome {
+    // Player 0 wins with four in column 0.
+    let state: Connect4 = "1212121".parse().unwrap();
+    match send(client, url, match_id, player, "Over", &state).await {
+        (Ok(_), _) => Outcome::Pass,
+        (Err(reason), exchange) => Outcome::Fail { reason, exchange },
+    }
+}
+
+/// Two matches at once. Agents that keep state per match have to keep them
+/// separate by `Gameplay-Match-ID`.
+async fn check_concurrent(client: &Client, url: &Url) -> Outcome {
+    let first_id = Uuid::now_v7();
+    let second_id = Uuid::now_v7();
+    let first = Connect4::default();
+    let second: Connect4 = "4".parse().unwrap();
+    let (first, second) = tokio::join!(
+        check_action(client, url, &first_id, &first, Expect::Valid),
+        check_action(client, url, &second_id, &second, Expect::Valid),
+    );
+    match (first, second) {
+        (Outcome::Pass, Outcome::Pass) => Outcome::Pass,
+        (Outcome::Fail { reason, exchange }, _) | (_, Outcome::Fail { reason, exchange }) => {
+            Outcome::Fail { reason, exchange }
+        }
+    }
+}
+
+async fn run_checks(client: &Client, url: &Url) -> Vec<Check> {
+    let match_id = Uuid::now_v7();
+    let mut checks = vec![];
+
+    let positions = [
+        ("empty board", Connect4::default(), Expect::Valid),
+        ("playing as player 1", "4".parse().unwrap(), Expect::Valid),
+        (
+            "only the last column open",
+            near_full(6, 5),
+            Expect::Column(6),
+        ),
+        (
+            "only a middle column open",
+            near_full(3, 0),
+            Expect::Column(3),
+        ),
+        // Player 0 has three in the bottom row.
+        ("forced win", "112233".parse().unwrap(), Expect::Column(3)),
+        // Player 1 has to stop three in the bottom row.
+        ("forced block", "17273".parse().unwrap(), Expect::Column(3)),
+    ];
+    for (name, state, expect) in positions {
+        let outcome = check_action(client, url, &match_id, &state, expect).await;
+        checks.push(Check { name, outcome });
+    }
+
+    checks.push(Check {
+        name: "match over (player 0)",
+        outcome: check_over(client, url, &match_id, 0).await,
+    });
+    checks.push(Check {
+        name: "match over (player 1)",
+        outcome: check_over(client, url, &match_id, 1).await,
+    });
+    checks.push(Check {
+        name: "concurrent matches",
+        outcome: check_concurrent(client, url).await,
+    });
+
+    checks
+}
+
+fn indent(text: &str, prefix: &str) -> String {
+    let mut out = String::new();
+    for line in text.lines() {
+        let _ = writeln!(out, "{}{}", prefix, line);
+    }
+    out
+}
+
+/// Run the suite for `game` against the agent at `url` and print the
+/// results. Returns whether every check passed.
+pub async fn test_agent(game: Game, url: &Url) -> bool {
+    let client = Client::new();
+    let checks = match game {
+        Game::Connect4 => run_checks(&client, url).await,
+    };
+
+    let mut passed = 0;
+    for check in &checks {
+        match &check.outcome {
+            Outcome::Pass => {
+                passed += 1;
+                println!("PASS  {}", check.name);
+            }
+            Outcome::Fail { reason, exchange } => {
+                println!("FAIL  {}", check.name);
+                println!("      {}", reason);
+                println!("      request:");
+                print!("{}", indent(&exchange.request, "        "));
+                println!("      response:");
+                print!("{}", indent(&exchange.response, "        "));
+            }
+        }
+    }
+    println!("{}/{} checks passed", passed, checks.len());
+
+    passed == checks.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+    use tokio::sync::Mutex;
+
+    use gameplay::agents::Greedy;
+    use gameplay::runner::{Local, Player, Turn};
+
+    use super::*;
+
+    /// Serve `agent` over the agent protocol on a local port. Just enough
+    /// HTTP for `send`, one request per connection.
+    async fn serve<P: Player<Connect4> + 'static>(agent: P) -> Url {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+        let agent = Arc::new(Mutex::new(agent));
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                let agent = agent.clone();
+                tokio::spawn(async move {
+                    let mut stream = BufReader::new(stream);
+                    let mut length = 0;
+                    let mut over = false;
+                    loop {
+                        let mut line = String::new();
+                        stream.read_line(&mut line).await.unwrap();
+                        let line = line.trim_end().to_ascii_lowercase();
+                        if line.is_empty() {
+                            break;
+                        }
+                        if let Some(value) = line.strip_prefix("content-length:") {
+                            length = value.trim().parse().unwrap();
+                        }
+                        over |= line == "gameplay-match-status: over";
+                    }
+                    let mut body = vec![0; length];
+                    stream.read_exact(&mut body).await.unwrap();
+                    let state: Connect4 = serde_json::from_slice(&body).unwrap();
+
+                    let body = if over {
+                        String::new()
+                    } else {
+                        let GameStatus::InProgress { next_player } = state.status() else {
+                            unreachable!("asked to play a finished game")
+                        };
+                        let turn = Turn {
+                            match_id: "test",
+                            player: next_player,
+                            move_time: None,
+                            remaining_time: None,
+                        };
+                        let action = agent.lock().await.choose(turn, &state).await.unwrap();
+                        serde_json::to_string(&action).unwrap()
+                    };
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    );
+                    stream.write_all(response.as_bytes()).await.unwrap();
+                });
+            }
+        });
+        url
+    }
+
+    async fn failed(agent: impl Player<Connect4> + 'static) -> Vec<&'static str> {
+        let url = serve(agent).await;
+        run_checks(&Client::new(), &url)
+            .await
+            .into_iter()
+            .filter(|check| matches!(check.outcome, Outcome::Fail { .. }))
+            .map(|check| check.name)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn greedy_agent_passes() {
+        let agent = Local::new("greedy", Greedy::new(Some(1)));
+        assert_eq!(failed(agent).await, Vec::<&str>::new());
+    }
+
+    #[tokio::test]
+    async fn first_column_agent_fails() {
+        let agent = Local::new("first", |_: &Connect4| Action { column: 0 });
+        assert_eq!(
+            failed(agent).await,
+            vec![
+                "only the last column open",
+                "only a middle column open",
+                "forced win",
+                "forced block"
+            ]
+        );
+    }
+}
@@ -0,0 +1,42 @@
+//! Matches between agents that run without a terminal.
+
+use std::io;
+
+use reqwest::Client;
+use uuid::Uuid;
+
+use gameplay::agents::BuiltinGame;
+use gameplay::games::{GameResult, GameState, GameStatus};
+use gameplay::record::MatchRecord;
+use gameplay::runner::{self, TimeControl};
+
+use crate::agents::AgentSpec;
+
+pub struct MatchOutcome<S: GameState> {
+    pub result: GameResult,
+    pub record: MatchRecord<S>,
+}
+
+/// Play a match between two agents. `players[i]` plays as player `i`.
+/// An agent that fails to give a valid action in time forfeits.
+pub async fn play_match<S: BuiltinGame>(
+    client: &Client,
+    players: [&AgentSpec; 2],
+    time: TimeControl,
+) -> io::Result<MatchOutcome<S>> {
+    let mut player0 = players[0].player::<S>(client)?;
+    let mut player1 = players[1].player::<S>(client)?;
+    let record = runner::run_match(
+        Uuid::now_v7().to_string(),
+        S::default(),
+        [player0.as_mut(), player1.as_mut()],
+        time,
+        |_| {},
+    )
+    .await;
+    let result = match record.status {
+        GameStatus::Over { result } => result,
+        GameStatus::InProgress { .. } => unreachable!("agents don't quit"),
+    };
+    Ok(MatchOutcome { result, record })
+}
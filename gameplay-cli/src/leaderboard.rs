@@ -0,0 +1,38 @@
+//! Rating leaderboard printed after a batch of matches.
+
+use gameplay::ratings::{self, Outcome};
+
+/// Print Elo and Glicko-2 ratings for `names`, best first by Elo.
+pub fn print(names: &[&str], outcomes: &[Outcome]) {
+    let elo = ratings::elo(names.len(), outcomes);
+    let glicko2 = ratings::glicko2(names.len(), outcomes);
+
+    let mut ranking: Vec<usize> = (0..names.len()).collect();
+    ranking.sort_by(|&a, &b| elo[b].rating.total_cmp(&elo[a].rating));
+
+    let width = names
+        .iter()
+        .map(|name| name.len())
+        .max()
+        .unwrap_or(0)
+        .max("agent".len());
+    println!(
+        "{:>4}  {:width$}  {:>19}  {:>19}",
+        "rank", "agent", "elo (95% CI)", "glicko-2 (95% CI)"
+    );
+    for (rank, &i) in ranking.iter().enumerate() {
+        let (elo_low, elo_high) = elo[i].interval();
+        let (glicko2_low, glicko2_high) = glicko2[i].rating.interval();
+        println!(
+            "{:>4}  {:width$}  {:>5.0} ({:>5.0}, {:>5.0})  {:>5.0} ({:>5.0}, {:>5.0})",
+            rank + 1,
+            names[i],
+            elo[i].rating,
+            elo_low,
+            elo_high,
+            glicko2[i].rating.rating,
+            glicko2_low,
+            glicko2_high
+        );
+    }
+}
@@ -1,124 +1,163 @@
 use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-use clap::{Parser, Subcommand};
-use reqwest::Url;
+use async_trait::async_trait;
+use clap::{Args, Parser, Subcommand};
+use reqwest::{Client, Url};
 use uuid::Uuid;
 
-use gameplay::games::connect4::{Action, Connect4};
-use gameplay::games::{Game, GameState, GameStatus};
+use agents::{AgentSpec, PlayerArgs};
+use tui::TuiGame;
 
+use gameplay::games::connect4::Connect4;
+use gameplay::games::{Game, GameStatus};
+use gameplay::record::{MatchRecord, PlayerDescriptor};
+use gameplay::runner::{self, Event, Player, PlayerError, TimeControl, Turn};
+use gameplay::sprt::Sprt;
+
+mod agents;
+mod analyze;
+mod bench;
+mod conformance;
+mod headless;
+mod leaderboard;
+mod records;
+mod replay;
+mod sprt;
+mod tournament;
 mod tui;
 
-enum Player {
-    Human,
-    Agent(Url),
-}
+/// A person playing at the terminal.
+struct Human;
 
-async fn cli_connect4_match(player0: Player, player1: Player) -> io::Result<()> {
-    // Wrap the match in setup/cleanup so we make sure to cleanup on any error.
-    tui::setup()?;
-    let result = _cli_connect4_match(player0, player1).await;
-    tui::cleanup()?;
-    result
-}
+#[async_trait]
+impl<S: TuiGame> Player<S> for Human {
+    fn descriptor(&self) -> PlayerDescriptor {
+        PlayerDescriptor::Human
+    }
 
-async fn _cli_connect4_match(player0: Player, player1: Player) -> io::Result<()> {
-    let client = reqwest::Client::new();
-
-    let match_id = Uuid::now_v7();
-    let mut state = Connect4::default();
-    let mut status = state.status();
-    while let GameStatus::InProgress { next_player } = status {
-        let player = match next_player {
-            0 => &player0,
-            1 => &player1,
-            _ => unreachable!(),
-        };
+    // Only agents are timed.
+    fn timed(&self) -> bool {
+        false
+    }
 
-        let action = match player {
-            Player::Human => {
-                tui::show_connect4(&state, true)?;
-                'turn: loop {
-                    let c = tui::read_char()?;
-                    if c == 'q' {
-                        return Ok(());
-                    }
-                    if let Some(c) = c.to_digit(10) {
-                        if c == 0 || c > 7 {
-                            continue 'turn;
-                        }
-                        let action = Action {
-                            column: (c - 1) as usize,
-                        };
-                        if state.valid_action(&action) {
-                            break 'turn action;
-                        }
-                    }
+    async fn choose(&mut self, _turn: Turn<'_>, state: &S) -> Result<S::Action, PlayerError> {
+        let read = || -> io::Result<Option<S::Action>> {
+            tui::show(state, true)?;
+            loop {
+                let c = tui::read_char()?;
+                if c == 'q' {
+                    return Ok(None);
                 }
-            }
-            Player::Agent(url) => {
-                tui::show_connect4(&state, false)?;
-                // Query the agent for an action
-                let resp = client.post(url.clone())
-                    .header("Gameplay-Game", "connect4")
-                    .header("Gameplay-Match-ID", match_id.to_string())
-                    .header("Gameplay-Player", next_player.to_string())
-                    .header("Gameplay-Match-Status", "InProgress")
-                    .json(&state).send().await;
-                match resp {
-                    Ok(resp) => {
-                        let action = resp.json::<Action>().await;
-                        match action {
-                            Ok(action) => {
-                                // See if the action is valid.
-                                if state.valid_action(&action) {
-                                    action
-                                } else {
-                                    tui::show_error(&format!("Action is invalid: {:?}", action))?;
-                                    while tui::read_char()? != 'q' {}
-                                    return Ok(());
-                                }
-                            }
-                            Err(err) => {
-                                tui::show_error(&err.to_string())?;
-                                while tui::read_char()? != 'q' {}
-                                return Ok(());
-                            }
-                        }
-                    }
-                    Err(err) => {
-                        tui::show_error(&err.to_string())?;
-                        while tui::read_char()? != 'q' {}
-                        return Ok(());
-                    }
+                if let Some(action) = state.action_for_key(c) {
+                    return Ok(Some(action));
                 }
             }
         };
-        status = state.apply_action(&action).unwrap();
+        match read() {
+            Ok(Some(action)) => Ok(action),
+            // Quitting, or losing the terminal, abandons the match.
+            Ok(None) | Err(_) => Err(PlayerError::Quit),
+        }
     }
-    // Tell the agents the match is over.
-    if let Player::Agent(url) = player0 {
-        let _ = client.post(url)
-            .header("Gameplay-Game", "connect4")
-            .header("Gameplay-Match-ID", match_id.to_string())
-            .header("Gameplay-Player", "0")
-            .header("Gameplay-Match-Status", "Over")
-            .json(&state)
-            .send().await;
+}
+
+/// A side without an agent is played by a human.
+fn player<S: TuiGame>(
+    client: &Client,
+    agent: Option<&AgentSpec>,
+) -> io::Result<Box<dyn Player<S>>> {
+    match agent {
+        Some(agent) => agent.player(client),
+        None => Ok(Box::new(Human)),
     }
-    if let Player::Agent(url) = player1 {
-        let _ = client.post(url)
-            .header("Gameplay-Game", "connect4")
-            .header("Gameplay-Match-ID", match_id.to_string())
-            .header("Gameplay-Player", "1")
-            .header("Gameplay-Match-Status", "Over")
-            .json(&state)
-            .send().await;
+}
+
+async fn cli_match<S: TuiGame>(
+    agents: [Option<AgentSpec>; 2],
+    time: TimeControl,
+    save: Option<&Path>,
+) -> io::Result<()> {
+    // Wrap the match in setup/cleanup so we make sure to cleanup on any error.
+    tui::setup()?;
+    let result = _cli_match::<S>(agents, time).await;
+    tui::cleanup()?;
+    let record = result?;
+
+    if let Some(path) = save {
+        records::save(path, &record)?;
     }
+    Ok(())
+}
+
+async fn _cli_match<S: TuiGame>(
+    agents: [Option<AgentSpec>; 2],
+    time: TimeControl,
+) -> io::Result<MatchRecord<S>> {
+    let client = Client::new();
+    let mut player0 = player::<S>(&client, agents[0].as_ref())?;
+    let mut player1 = player::<S>(&client, agents[1].as_ref())?;
+    // The board is shown while agents think, humans show it themselves.
+    let agents = agents.map(|agent| agent.is_some());
 
-    tui::show_connect4(&state, false)?;
+    let mut shown: io::Result<()> = Ok(());
+    let mut last = S::default();
+    let record = runner::run_match(
+        Uuid::now_v7().to_string(),
+        S::default(),
+        [player0.as_mut(), player1.as_mut()],
+        time,
+        |event| match event {
+            Event::Turn { player, state } if agents[player] && shown.is_ok() => {
+                shown = tui::show(state, false);
+            }
+            Event::Over { state, .. } => last = state.clone(),
+            _ => {}
+        },
+    )
+    .await;
+    shown?;
+
+    match (record.status, &record.error) {
+        // Somebody quit.
+        (GameStatus::InProgress { .. }, _) => return Ok(record),
+        (GameStatus::Over { result }, Some(reason)) => tui::show_forfeit(&last, result, reason)?,
+        (GameStatus::Over { .. }, None) => tui::show(&last, false)?,
+    }
     while tui::read_char()? != 'q' {}
-    Ok(())
+    Ok(record)
+}
+
+/// The agents for commands that don't have a human player.
+fn both_agents(players: PlayerArgs) -> [AgentSpec; 2] {
+    match players.agents() {
+        [Some(a), Some(b)] => [a, b],
+        _ => {
+            eprintln!("Both players need an agent, {}.", PlayerArgs::USAGE);
+            std::process::exit(2);
+        }
+    }
+}
+
+/// Time limits for agents. An agent that goes over forfeits the match.
+#[derive(Args, Clone, Copy)]
+struct TimeArgs {
+    /// Milliseconds an agent has for each move
+    #[arg(long)]
+    move_time_ms: Option<u64>,
+    /// Milliseconds an agent has for all of its moves in a match
+    #[arg(long)]
+    match_time_ms: Option<u64>,
+}
+
+impl From<TimeArgs> for TimeControl {
+    fn from(args: TimeArgs) -> Self {
+        TimeControl {
+            move_time: args.move_time_ms.map(Duration::from_millis),
+            match_time: args.match_time_ms.map(Duration::from_millis),
+        }
+    }
 }
 
 #[derive(Parser)]
@@ -132,39 +171,174 @@ struct Cli {
 
 #[derive(Subcommand)]
 enum Commands {
-    /// Play a match
+    /// Play a match, you play any side that doesn't have an agent
     Play {
+        #[command(flatten)]
+        players: PlayerArgs,
+        #[command(flatten)]
+        time: TimeArgs,
+        /// Save a record of the match to this file as json
         #[arg(long)]
-        player0_url: Option<Url>,
+        save: Option<PathBuf>,
+    },
+    /// Play a batch of matches between two agents without the TUI
+    Bench {
+        #[command(flatten)]
+        players: PlayerArgs,
+        /// Number of matches to play, alternating who goes first
+        #[arg(long, default_value_t = 10)]
+        games: usize,
+        #[command(flatten)]
+        time: TimeArgs,
+        /// Save a record of every match to this directory as json
+        #[arg(long)]
+        save: Option<PathBuf>,
+    },
+    /// Keep playing pairs of matches until an SPRT decides whether the
+    /// player 0 agent is stronger than the player 1 agent
+    Sprt {
+        #[command(flatten)]
+        players: PlayerArgs,
+        /// Elo gain under the null hypothesis
+        #[arg(long, default_value_t = 0.0)]
+        elo0: f64,
+        /// Elo gain under the alternative hypothesis
+        #[arg(long, default_value_t = 10.0)]
+        elo1: f64,
+        /// Chance of accepting elo1 when elo0 is true
+        #[arg(long, default_value_t = 0.05)]
+        alpha: f64,
+        /// Chance of accepting elo0 when elo1 is true
+        #[arg(long, default_value_t = 0.05)]
+        beta: f64,
+        /// Give up without a decision after this many matches
+        #[arg(long, default_value_t = 10000)]
+        max_games: usize,
+        #[command(flatten)]
+        time: TimeArgs,
+        /// Save a record of every match to this directory as json
         #[arg(long)]
-        player1_url: Option<Url>,
+        save: Option<PathBuf>,
     },
-    // Test an agent
-    // Test { url: Url },
+    /// Play a round-robin tournament between agents
+    Tournament {
+        /// Agent to enter, a url, `cmd:` and a command or `builtin:` and a builtin agent. Can be given more than once
+        #[arg(long = "agent")]
+        agents: Vec<AgentSpec>,
+        /// File listing agents to enter, one `[name] agent` per line
+        #[arg(long)]
+        config: Option<PathBuf>,
+        /// Number of matches each agent plays as player 0 against each other agent
+        #[arg(long, default_value_t = 1)]
+        games: usize,
+        #[command(flatten)]
+        time: TimeArgs,
+        /// Save a record of every match to this directory as json
+        #[arg(long)]
+        save: Option<PathBuf>,
+    },
+    /// Evaluate every move in a position, connect4 only
+    Analyze {
+        /// The columns played so far counting from 1, like `4453`, a board
+        /// diagram or a board as json. Leave it out for the start of the game
+        position: Option<String>,
+        /// Time to spend on each move before falling back to a search
+        #[arg(long, default_value_t = 1000)]
+        time_ms: u64,
+    },
+    /// Step through a saved match
+    Replay { file: PathBuf },
+    /// Test an agent against scripted positions
+    Test { url: Url },
 }
 
 #[tokio::main]
 async fn main() -> io::Result<()> {
     let args = Cli::parse();
 
-    // Only one game so far, everything assumes connect4.
-    assert_eq!(args.game, Game::Connect4);
+    match args.game {
+        Game::Connect4 => run::<Connect4>(args.command).await,
+    }
+}
 
-    match args.command {
+/// Run a command for the game `S`.
+async fn run<S: TuiGame>(command: Commands) -> io::Result<()> {
+    match command {
         Commands::Play {
-            player0_url,
-            player1_url,
+            players,
+            time,
+            save,
         } => {
-            let player0 = match player0_url {
-                Some(url) => Player::Agent(url),
-                None => Player::Human,
+            cli_match::<S>(players.agents(), time.into(), save.as_deref()).await?;
+        }
+        Commands::Bench {
+            players,
+            games,
+            time,
+            save,
+        } => {
+            let [a, b] = both_agents(players);
+            bench::bench::<S>(&a, &b, games, time.into(), save.as_deref()).await?;
+        }
+        Commands::Sprt {
+            players,
+            elo0,
+            elo1,
+            alpha,
+            beta,
+            max_games,
+            time,
+            save,
+        } => {
+            let test = Sprt {
+                elo0,
+                elo1,
+                alpha,
+                beta,
             };
-            let player1 = match player1_url {
-                Some(url) => Player::Agent(url),
-                None => Player::Human,
+            let [candidate, baseline] = both_agents(players);
+            sprt::sprt::<S>(
+                &candidate,
+                &baseline,
+                test,
+                max_games,
+                time.into(),
+                save.as_deref(),
+            )
+            .await?;
+        }
+        Commands::Tournament {
+            agents,
+            config,
+            games,
+            time,
+            save,
+        } => {
+            let mut entrants = match config {
+                Some(path) => tournament::read_config(&path)?,
+                None => vec![],
             };
-            cli_connect4_match(player0, player1).await?;
-        } // Commands::Test { url } => {}
+            entrants.extend(agents.into_iter().map(tournament::Entrant::from_agent));
+            if entrants.len() < 2 {
+                eprintln!("A tournament needs at least two agents.");
+                std::process::exit(2);
+            }
+            tournament::tournament::<S>(&entrants, games, time.into(), save.as_deref()).await?;
+        }
+        Commands::Replay { file } => {
+            replay::replay::<S>(&file)?;
+        }
+        Commands::Analyze { position, time_ms } => {
+            S::analyze(
+                position.as_deref().unwrap_or_default(),
+                Duration::from_millis(time_ms),
+            )?;
+        }
+        Commands::Test { url } => {
+            if !conformance::test_agent(S::GAME, &url).await {
+                std::process::exit(1);
+            }
+        }
     }
 
     Ok(())
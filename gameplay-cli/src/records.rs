@@ -0,0 +1,40 @@
+//! Saving and loading match records as json.
+
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use serde::Deserialize;
+
+use gameplay::games::{Game, GameState};
+use gameplay::record::MatchRecord;
+
+pub fn save<S: GameState>(path: &Path, record: &MatchRecord<S>) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    serde_json::to_writer_pretty(&mut writer, record)?;
+    writer.write_all(b"\n")?;
+    writer.flush()
+}
+
+pub fn load<S: GameState>(path: &Path) -> io::Result<MatchRecord<S>> {
+    let reader = BufReader::new(File::open(path)?);
+    Ok(serde_json::from_reader(reader)?)
+}
+
+/// Read which game a saved record is for, without reading the rest of it.
+pub fn load_game(path: &Path) -> io::Result<Game> {
+    #[derive(Deserialize)]
+    struct Header {
+        game: Game,
+    }
+
+    let reader = BufReader::new(File::open(path)?);
+    let header: Header = serde_json::from_reader(reader)?;
+    Ok(header.game)
+}
+
+/// Save the record in `dir` as `<match_id>.json`, creating `dir` if needed.
+pub fn save_in_dir<S: GameState>(dir: &Path, record: &MatchRecord<S>) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    save(&dir.join(format!("{}.json", record.match_id)), record)
+}
@@ -0,0 +1,98 @@
+//! Step through a saved match in the TUI.
+
+use std::io;
+use std::path::Path;
+
+use crossterm::event::KeyCode;
+
+use gameplay::games::GameStatus;
+use gameplay::record::MatchRecord;
+
+use crate::records;
+use crate::tui::{self, TuiGame};
+
+pub fn replay<S: TuiGame>(path: &Path) -> io::Result<()> {
+    // Check the game before trying to read the states as `S`.
+    let game = records::load_game(path)?;
+    if game != S::GAME {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} is a {} match", path.display(), game),
+        ));
+    }
+    let record: MatchRecord<S> = records::load(path)?;
+    // Check every move replays before taking over the terminal.
+    let mut state = record.start.clone();
+    for (i, played) in record.moves.iter().enumerate() {
+        state.apply_action(&played.action).map_err(|err| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("move {} can't be replayed: {}", i + 1, err),
+            )
+        })?;
+    }
+
+    tui::setup()?;
+    let result = step_through(&record, state);
+    tui::cleanup()?;
+    result
+}
+
+fn info<S: TuiGame>(record: &MatchRecord<S>, shown: usize) -> Vec<String> {
+    let mut lines = vec![format!("match {}", record.match_id)];
+    for (player, descriptor) in record.players.iter().enumerate() {
+        lines.push(format!("{}: {}", S::player_name(player), descriptor));
+    }
+    if shown == 0 {
+        lines.push(format!("start (0/{} moves)", record.moves.len()));
+    } else {
+        let played = &record.moves[shown - 1];
+        lines.push(format!(
+            "move {}/{}: {} {} in {} ms",
+            shown,
+            record.moves.len(),
+            S::player_name(played.player),
+            S::describe_action(&played.action),
+            played.latency_ms
+        ));
+    }
+    if shown == record.moves.len() {
+        if let GameStatus::InProgress { .. } = record.status {
+            lines.push("the match ended early".to_string());
+        }
+        if let Some(error) = &record.error {
+            lines.push(format!("error: {}", error));
+        }
+    }
+    lines
+}
+
+/// Step through the match from its `end`, applying and undoing moves to
+/// show the one picked.
+fn step_through<S: TuiGame>(record: &MatchRecord<S>, end: S) -> io::Result<()> {
+    let last = record.moves.len();
+    let mut state = end;
+    let mut played = last;
+    let mut shown = 0;
+    loop {
+        while played > shown {
+            played -= 1;
+            let action = &record.moves[played].action;
+            state.unapply_action(action).expect("moves were replayed");
+        }
+        while played < shown {
+            let action = &record.moves[played].action;
+            state.apply_action(action).expect("moves were replayed");
+            played += 1;
+        }
+        tui::show_replay(&state, &info(record, shown))?;
+        match tui::read_key()? {
+            KeyCode::Char('q') => return Ok(()),
+            KeyCode::Left => shown = shown.saturating_sub(1),
+            KeyCode::Right => shown = (shown + 1).min(last),
+            KeyCode::Home => shown = 0,
+            KeyCode::End => shown = last,
+            _ => {}
+        }
+    }
+}
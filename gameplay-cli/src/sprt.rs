@@ -0,0 +1,109 @@
+//! Play color swapped pairs of matches until an SPRT decides whether one
+//! agent is stronger than another.
+
+use std::io;
+use std::path::Path;
+
+use reqwest::Client;
+
+use gameplay::agents::BuiltinGame;
+use gameplay::games::GameResult;
+use gameplay::runner::TimeControl;
+use gameplay::sprt::{Decision, Sprt, Tally};
+
+use crate::agents::AgentSpec;
+use crate::{headless, records};
+
+/// Test whether `candidate` is `sprt.elo1` stronger than `baseline`.
+/// Stops when the test decides or after `max_games`. Records of the matches
+/// are saved in `save`.
+pub async fn sprt<S: BuiltinGame>(
+    candidate: &AgentSpec,
+    baseline: &AgentSpec,
+    sprt: Sprt,
+    max_games: usize,
+    time: TimeControl,
+    save: Option<&Path>,
+) -> io::Result<()> {
+    let client = Client::new();
+    let (lower, upper) = sprt.bounds();
+    let mut tally = Tally::default();
+    let mut forfeits = 0;
+    let mut decision = Decision::Continue;
+
+    while tally.games() < max_games {
+        // The candidate plays as player `candidate_player`. An odd
+        // `max_games` ends halfway through a pair.
+        for candidate_player in 0..2 {
+            if tally.games() == max_games {
+                break;
+            }
+            let players = if candidate_player == 0 {
+                [candidate, baseline]
+            } else {
+                [baseline, candidate]
+            };
+            let outcome = headless::play_match::<S>(&client, players, time).await?;
+            if let Some(dir) = save {
+                records::save_in_dir(dir, &outcome.record)?;
+            }
+            match outcome.result {
+                GameResult::Tie => tally.ties += 1,
+                result if result.winner() == Some(candidate_player) => tally.wins += 1,
+                _ => tally.losses += 1,
+            }
+            if let GameResult::Forfeit {
+                faulted_player,
+                fault,
+                ..
+            } = outcome.result
+            {
+                forfeits += 1;
+                eprintln!(
+                    "{} forfeited ({}): {}",
+                    players[faulted_player],
+                    fault,
+                    outcome.record.error.as_deref().unwrap_or_default()
+                );
+            }
+        }
+
+        decision = sprt.decide(&tally);
+        eprintln!(
+            "games {}: W {} L {} T {}, LLR {:.2} ({:.2}, {:.2})",
+            tally.games(),
+            tally.wins,
+            tally.losses,
+            tally.ties,
+            sprt.llr(&tally),
+            lower,
+            upper
+        );
+        if decision != Decision::Continue {
+            break;
+        }
+    }
+
+    println!(
+        "elo0 {}, elo1 {}, alpha {}, beta {}",
+        sprt.elo0, sprt.elo1, sprt.alpha, sprt.beta
+    );
+    println!(
+        "games {}: W {} L {} T {} ({} forfeited)",
+        tally.games(),
+        tally.wins,
+        tally.losses,
+        tally.ties,
+        forfeits
+    );
+    println!("LLR {:.2} ({:.2}, {:.2})", sprt.llr(&tally), lower, upper);
+    if let Some((elo, margin)) = tally.elo() {
+        println!("elo difference {:.1} +/- {:.1}", elo, margin);
+    }
+    match decision {
+        Decision::AcceptH1 => println!("H1 accepted, {} is stronger", candidate),
+        Decision::AcceptH0 => println!("H0 accepted, {} is not stronger", candidate),
+        Decision::Continue => println!("No decision after {} games", tally.games()),
+    }
+    Ok(())
+}
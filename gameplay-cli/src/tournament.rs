@@ -0,0 +1,285 @@
+//! Round-robin tournaments between agents.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use reqwest::Client;
+
+use gameplay::agents::BuiltinGame;
+use gameplay::games::GameResult;
+use gameplay::ratings::Outcome;
+use gameplay::runner::TimeControl;
+
+use crate::agents::AgentSpec;
+use crate::{headless, leaderboard, records};
+
+pub struct Entrant {
+    pub name: String,
+    pub agent: AgentSpec,
+}
+
+impl Entrant {
+    pub fn from_agent(agent: AgentSpec) -> Self {
+        Self {
+            name: agent.to_string(),
+            agent,
+        }
+    }
+}
+
+/// Read entrants from a config file. Each line is an agent url, `cmd:` and
+/// a command or `builtin:` and a builtin agent, optionally preceded by a
+/// name for it. Blank lines and lines starting with `#` are ignored.
+///
+/// ```text
+/// # name   agent
+/// rand     http://saolsen_connect4_rand
+/// mcts     http://saolsen_connect4_mcts
+/// local    cmd:./target/release/my_agent --depth 4
+/// minimax  builtin:minimax?depth=6
+/// http://localhost:8000
+/// ```
+pub fn read_config(path: &Path) -> io::Result<Vec<Entrant>> {
+    let mut entrants = vec![];
+    for (i, line) in fs::read_to_string(path)?.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let invalid = |msg: String| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}:{}: {}", path.display(), i + 1, msg),
+            )
+        };
+        // Commands have spaces in them, so the name is only split off if
+        // the line doesn't start with the agent.
+        let (name, agent) = match line.split_once(char::is_whitespace) {
+            Some((name, agent)) if !name.starts_with("cmd:") => (Some(name), agent.trim()),
+            _ => (None, line),
+        };
+        let agent: AgentSpec = agent.parse().map_err(invalid)?;
+        entrants.push(match name {
+            Some(name) => Entrant {
+                name: name.to_string(),
+                agent,
+            },
+            None => Entrant::from_agent(agent),
+        });
+    }
+    Ok(entrants)
+}
+
+#[derive(Default, Clone)]
+struct Standing {
+    wins: usize,
+    losses: usize,
+    ties: usize,
+    forfeits: usize,
+}
+
+impl Standing {
+    fn points(&self) -> f64 {
+        self.wins as f64 + self.ties as f64 * 0.5
+    }
+}
+
+/// Play every pairing of entrants `games` times with each of them going
+/// first, then print the standings. A win is worth 1 point and a tie ½.
+/// Records of the matches are saved in `save`.
+pub async fn tournament<S: BuiltinGame>(
+    entrants: &[Entrant],
+    games: usize,
+    time: TimeControl,
+    save: Option<&Path>,
+) -> io::Result<()> {
+    let client = Client::new();
+    let n = entrants.len();
+    let mut standings = vec![Standing::default(); n];
+    // head_to_head[i][j] is the points entrant i scored against entrant j.
+    let mut head_to_head = vec![vec![0.0; n]; n];
+    let mut outcomes = vec![];
+
+    let total = n * n.saturating_sub(1) * games;
+    let mut played = 0;
+    for i in 0..n {
+        for j in 0..n {
+            if i == j {
+                continue;
+            }
+            for _ in 0..games {
+                let order = [i, j];
+                let outcome = headless::play_match::<S>(
+                    &client,
+                    [&entrants[order[0]].agent, &entrants[order[1]].agent],
+                    time,
+                )
+                .await?;
+                played += 1;
+                outcomes.push(Outcome {
+                    players: order,
+                    result: outcome.result,
+                });
+                if let Some(dir) = save {
+                    records::save_in_dir(dir, &outcome.record)?;
+                }
+
+                let summary = match outcome.result {
+                    GameResult::Winner { winning_player, .. } => {
+                        let winner = order[winning_player];
+                        let loser = order[1 - winning_player];
+                        standings[winner].wins += 1;
+                        standings[loser].losses += 1;
+                        head_to_head[winner][loser] += 1.0;
+                        format!("{} beat {}", entrants[winner].name, entrants[loser].name)
+                    }
+                    GameResult::Tie => {
+                        standings[i].ties += 1;
+                        standings[j].ties += 1;
+                        head_to_head[i][j] += 0.5;
+                        head_to_head[j][i] += 0.5;
+                        format!("{} tied {}", entrants[i].name, entrants[j].name)
+                    }
+                    GameResult::Forfeit {
+                        faulted_player,
+                        fault,
+                        winning_player,
+                    } => {
+                        let winner = order[winning_player];
+                        let loser = order[faulted_player];
+                        standings[winner].wins += 1;
+                        standings[loser].losses += 1;
+                        standings[loser].forfeits += 1;
+                        head_to_head[winner][loser] += 1.0;
+                        format!(
+                            "{} beat {}, {} forfeited ({}): {}",
+                            entrants[winner].name,
+                            entrants[loser].name,
+                            entrants[loser].name,
+                            fault,
+                            outcome.record.error.as_deref().unwrap_or_default()
+                        )
+                    }
+                };
+                eprintln!(
+                    "match {}/{}: {} vs {}: {}",
+                    played, total, entrants[i].name, entrants[j].name, summary
+                );
+            }
+        }
+    }
+
+    print_standings(entrants, &standings, &head_to_head);
+    println!();
+    let names: Vec<&str> = entrants.iter().map(|e| e.name.as_str()).collect();
+    leaderboard::print(&names, &outcomes);
+    Ok(())
+}
+
+fn print_standings(entrants: &[Entrant], standings: &[Standing], head_to_head: &[Vec<f64>]) {
+    let mut ranking: Vec<usize> = (0..entrants.len()).collect();
+    ranking.sort_by(|&a, &b| standings[b].points().total_cmp(&standings[a].points()));
+
+    let width = entrants
+        .iter()
+        .map(|entrant| entrant.name.len())
+        .max()
+        .unwrap_or(0)
+        .max("agent".len());
+
+    println!(
+        "{:>4}  {:width$}  {:>6}  {:>4}  {:>4}  {:>4}  {:>8}",
+        "rank", "agent", "points", "W", "L", "T", "forfeits"
+    );
+    for (rank, &i) in ranking.iter().enumerate() {
+        let standing = &standings[i];
+        println!(
+            "{:>4}  {:width$}  {:>6.1}  {:>4}  {:>4}  {:>4}  {:>8}",
+            rank + 1,
+            entrants[i].name,
+            standing.points(),
+            standing.wins,
+            standing.losses,
+            standing.ties,
+            standing.forfeits
+        );
+    }
+
+    // Head to head, rows are the points the row agent scored against the
+    // column agent. Columns are numbered by rank to keep the table narrow.
+    let label_width = width + 4;
+    println!();
+    print!("{:label_width$}", "");
+    for rank in 0..ranking.len() {
+        print!("  {:>5}", rank + 1);
+    }
+    println!();
+    for (rank, &i) in ranking.iter().enumerate() {
+        print!(
+            "{:label_width$}",
+            format!("{:>2} {}", rank + 1, entrants[i].name)
+        );
+        for &j in &ranking {
+            if i == j {
+                print!("  {:>5}", "-");
+            } else {
+                print!("  {:>5.1}", head_to_head[i][j]);
+            }
+        }
+        println!();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_config() {
+        let path = std::env::temp_dir().join(format!("gameplay-config-{}", std::process::id()));
+        fs::write(
+            &path,
+            "# name   agent\n\
+             rand     http://localhost:8000/\n\
+             \n\
+             local    cmd:./my_agent --depth 4\n\
+             cmd:./other_agent --fast\n\
+             builtin:minimax?depth=2\n",
+        )
+        .unwrap();
+        let entrants = read_config(&path);
+        fs::remove_file(&path).unwrap();
+
+        let entrants: Vec<(String, String)> = entrants
+            .unwrap()
+            .into_iter()
+            .map(|entrant| (entrant.name, entrant.agent.to_string()))
+            .collect();
+        let expected = [
+            ("rand", "http://localhost:8000/"),
+            ("local", "./my_agent --depth 4"),
+            ("./other_agent --fast", "./other_agent --fast"),
+            ("builtin:minimax?depth=2", "builtin:minimax?depth=2"),
+        ];
+        assert_eq!(
+            entrants,
+            expected.map(|(name, agent)| (name.to_string(), agent.to_string()))
+        );
+    }
+
+    #[test]
+    fn config_errors_have_the_line() {
+        let path = std::env::temp_dir().join(format!("gameplay-bad-config-{}", std::process::id()));
+        fs::write(
+            &path,
+            "# agents\nrand http://localhost:8000/\nmcts not a url\n",
+        )
+        .unwrap();
+        let err = read_config(&path).err().unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains(":3:"), "{}", err);
+    }
+}
@@ -0,0 +1,121 @@
+use std::io;
+use std::time::Duration;
+
+use crossterm::{cursor, queue, style};
+
+use gameplay::games::connect4::{Action, Connect4, COLS, ROWS};
+use gameplay::games::{GameResult, GameState, GameStatus};
+
+use super::TuiGame;
+use crate::analyze;
+
+const BORDER: &str = "+---+---+---+---+---+---+---+";
+
+impl TuiGame for Connect4 {
+    const PROMPT: &'static str = "choose a column (1-7)";
+
+    fn player_name(player: usize) -> &'static str {
+        match player {
+            0 => "Blue",
+            1 => "Red",
+            _ => unreachable!("Invalid player"),
+        }
+    }
+
+    fn player_color(player: usize) -> style::Color {
+        match player {
+            0 => style::Color::Blue,
+            1 => style::Color::Red,
+            _ => unreachable!("Invalid player"),
+        }
+    }
+
+    fn queue_board(&self, stdout: &mut io::Stdout, your_turn: bool) -> io::Result<()> {
+        if your_turn {
+            for column in 0..COLS {
+                if self.valid_action(&Action { column }) {
+                    queue!(stdout, style::Print(format!("  {} ", column + 1)))?;
+                } else {
+                    queue!(stdout, style::Print("    "))?;
+                }
+            }
+        }
+        queue!(
+            stdout,
+            cursor::MoveToNextLine(1),
+            style::Print(BORDER),
+            cursor::MoveToNextLine(1)
+        )?;
+        // The four that won are drawn differently so they stand out.
+        let line: Vec<(usize, usize)> = match self.status() {
+            GameStatus::Over {
+                result:
+                    GameResult::Winner {
+                        line: Some(line), ..
+                    },
+            } => line.cells().collect(),
+            _ => vec![],
+        };
+        for row in (0..ROWS).rev() {
+            for col in 0..COLS {
+                queue!(stdout, style::Print("| "))?;
+                let chip = if line.contains(&(col, row)) {
+                    "◆"
+                } else {
+                    "●"
+                };
+                match self.get(col, row) {
+                    Some(0) => {
+                        queue!(
+                            stdout,
+                            style::SetForegroundColor(style::Color::Blue),
+                            style::Print(chip),
+                            style::ResetColor
+                        )?;
+                    }
+                    Some(1) => {
+                        queue!(
+                            stdout,
+                            style::SetForegroundColor(style::Color::Red),
+                            style::Print(chip),
+                            style::ResetColor
+                        )?;
+                    }
+                    None => {
+                        queue!(stdout, style::Print(" "))?;
+                    }
+                    _ => unreachable!("Invalid player"),
+                };
+                queue!(stdout, style::Print(" "))?;
+            }
+            queue!(
+                stdout,
+                style::Print("|"),
+                cursor::MoveToNextLine(1),
+                style::Print(BORDER),
+                cursor::MoveToNextLine(1)
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn action_for_key(&self, key: char) -> Option<Action> {
+        let c = key.to_digit(10)?;
+        if c == 0 || c as usize > COLS {
+            return None;
+        }
+        let action = Action {
+            column: (c - 1) as usize,
+        };
+        self.valid_action(&action).then_some(action)
+    }
+
+    fn describe_action(action: &Action) -> String {
+        format!("played column {}", action.column + 1)
+    }
+
+    fn analyze(position: &str, time: Duration) -> io::Result<()> {
+        analyze::analyze(&analyze::parse_position(position)?, time)
+    }
+}
@@ -0,0 +1,211 @@
+use std::io;
+use std::io::Write;
+use std::time::Duration;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind};
+use crossterm::{
+    cursor,
+    event::{self, Event},
+    execute, queue, style,
+    terminal::{self, ClearType},
+};
+
+use gameplay::agents::BuiltinGame;
+use gameplay::games::{GameResult, GameStatus};
+
+mod connect4;
+
+/// A game that can be shown and played in the TUI.
+pub trait TuiGame: BuiltinGame {
+    /// What a human is asked to do on their turn.
+    const PROMPT: &'static str;
+
+    fn player_name(player: usize) -> &'static str;
+    fn player_color(player: usize) -> style::Color;
+    /// Queue the board. On `your_turn` also show which inputs are valid.
+    fn queue_board(&self, stdout: &mut io::Stdout, your_turn: bool) -> io::Result<()>;
+    /// The action a key press picks, if it picks a valid one.
+    fn action_for_key(&self, key: char) -> Option<Self::Action>;
+    /// Describe an action, like "played column 4".
+    fn describe_action(action: &Self::Action) -> String;
+    /// Print the value of every action in `position`, spending up to `time`
+    /// on each. Only games with a solver can be analyzed.
+    fn analyze(_position: &str, _time: Duration) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("analyze doesn't support {}", Self::GAME),
+        ))
+    }
+}
+
+pub fn read_char() -> io::Result<char> {
+    loop {
+        if let Event::Key(KeyEvent {
+            code: KeyCode::Char(c),
+            kind: KeyEventKind::Press,
+            modifiers: _,
+            state: _,
+        }) = event::read()?
+        {
+            return Ok(c);
+        }
+    }
+}
+
+pub fn read_key() -> io::Result<KeyCode> {
+    loop {
+        if let Event::Key(KeyEvent {
+            code,
+            kind: KeyEventKind::Press,
+            modifiers: _,
+            state: _,
+        }) = event::read()?
+        {
+            return Ok(code);
+        }
+    }
+}
+
+pub fn show<S: TuiGame>(state: &S, your_turn: bool) -> io::Result<()> {
+    let mut stdout = io::stdout();
+    queue_game(&mut stdout, state, state.status(), your_turn)?;
+
+    if your_turn {
+        queue!(stdout, style::Print(format!("{} or ", S::PROMPT)))?;
+    }
+
+    queue!(
+        stdout,
+        style::Print("hit 'q' to quit"),
+        cursor::MoveToNextLine(1),
+        style::ResetColor
+    )?;
+
+    stdout.flush()
+}
+
+/// Show a position from a replay. `info` lines are shown under the board.
+pub fn show_replay<S: TuiGame>(state: &S, info: &[String]) -> io::Result<()> {
+    let mut stdout = io::stdout();
+    queue_game(&mut stdout, state, state.status(), false)?;
+
+    for line in info {
+        queue!(stdout, style::Print(line), cursor::MoveToNextLine(1))?;
+    }
+
+    queue!(
+        stdout,
+        style::Print("left/right to step through moves, home/end to jump, hit 'q' to quit"),
+        cursor::MoveToNextLine(1),
+        style::ResetColor
+    )?;
+
+    stdout.flush()
+}
+
+/// Show the board at the end of a match an agent forfeited, with the reason.
+pub fn show_forfeit<S: TuiGame>(state: &S, result: GameResult, reason: &str) -> io::Result<()> {
+    let mut stdout = io::stdout();
+    queue_game(&mut stdout, state, GameStatus::Over { result }, false)?;
+
+    queue!(
+        stdout,
+        style::SetForegroundColor(style::Color::Red),
+        style::Print(reason),
+        style::ResetColor,
+        cursor::MoveToNextLine(1),
+        style::Print("hit 'q' to quit"),
+        cursor::MoveToNextLine(1),
+    )?;
+
+    stdout.flush()
+}
+
+fn queue_player<S: TuiGame>(stdout: &mut io::Stdout, player: usize) -> io::Result<()> {
+    queue!(
+        stdout,
+        style::SetForegroundColor(S::player_color(player)),
+        style::Print(S::player_name(player)),
+    )
+}
+
+/// Queue the status line and the board. `status` is passed in because a
+/// match can be over before the board says so, when an agent forfeits.
+fn queue_game<S: TuiGame>(
+    stdout: &mut io::Stdout,
+    state: &S,
+    status: GameStatus,
+    your_turn: bool,
+) -> io::Result<()> {
+    // Header
+    queue!(
+        stdout,
+        terminal::Clear(ClearType::All),
+        style::SetForegroundColor(style::Color::Black),
+        style::SetBackgroundColor(style::Color::White),
+        cursor::MoveTo(0, 0)
+    )?;
+
+    match status {
+        GameStatus::InProgress { next_player } => {
+            queue_player::<S>(stdout, next_player)?;
+            queue!(stdout, style::ResetColor, style::Print("'s turn"),)?;
+            if your_turn {
+                queue!(stdout, style::Print(" (that's you)"))?;
+            }
+            queue!(stdout, cursor::MoveToNextLine(1))?;
+        }
+        GameStatus::Over { result } => match result {
+            GameResult::Winner { winning_player, .. } => {
+                queue_player::<S>(stdout, winning_player)?;
+                queue!(
+                    stdout,
+                    style::ResetColor,
+                    style::Print(" wins!"),
+                    cursor::MoveToNextLine(1)
+                )?;
+            }
+            GameResult::Tie => {
+                queue!(
+                    stdout,
+                    style::Print("It's a Tie"),
+                    cursor::MoveToNextLine(1)
+                )?;
+            }
+            GameResult::Forfeit {
+                faulted_player,
+                fault,
+                winning_player,
+            } => {
+                queue_player::<S>(stdout, winning_player)?;
+                queue!(stdout, style::ResetColor, style::Print(" wins! "))?;
+                queue_player::<S>(stdout, faulted_player)?;
+                queue!(
+                    stdout,
+                    style::ResetColor,
+                    style::Print(format!(" forfeited ({})", fault)),
+                    cursor::MoveToNextLine(1)
+                )?;
+            }
+        },
+    }
+
+    state.queue_board(stdout, your_turn)
+}
+
+pub fn setup() -> io::Result<()> {
+    let mut stdout = io::stdout();
+    execute!(stdout, terminal::EnterAlternateScreen)?;
+    terminal::enable_raw_mode()
+}
+
+pub fn cleanup() -> io::Result<()> {
+    let mut stdout = io::stdout();
+    execute!(
+        stdout,
+        style::ResetColor,
+        cursor::Show,
+        terminal::LeaveAlternateScreen
+    )?;
+    terminal::disable_raw_mode()
+}
@@ -0,0 +1,105 @@
+//! How fast Connect4 states play, which is most of the time playout heavy
+//! agents like MCTS spend. Run with `cargo bench -p gameplay`, and
+//! `--features bench` to compare against scanning the whole board.
+
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use gameplay::games::connect4::{Action, Bitboard, Connect4};
+use gameplay::games::{GameState, GameStatus};
+
+/// A position from the middle of a game, with most of the board left open.
+const MIDDLE: &str = "4453363522";
+
+/// Play random actions until the game is over.
+fn playout<S: GameState>(mut state: S, rng: &mut StdRng) -> GameStatus {
+    loop {
+        let action = state.random_action(rng).unwrap();
+        let status = state.apply_action(&action).unwrap();
+        if let GameStatus::Over { .. } = status {
+            return status;
+        }
+    }
+}
+
+fn playouts(c: &mut Criterion) {
+    let mut group = c.benchmark_group("playout");
+    let mut rng = StdRng::seed_from_u64(1);
+    group.bench_function("connect4", |b| {
+        b.iter(|| playout(Connect4::default(), &mut rng))
+    });
+    let mut rng = StdRng::seed_from_u64(1);
+    group.bench_function("bitboard", |b| {
+        b.iter(|| playout(Bitboard::default(), &mut rng))
+    });
+    group.finish();
+}
+
+fn apply_action(c: &mut Criterion) {
+    let mut group = c.benchmark_group("apply_action");
+    let action = Action { column: 3 };
+    let state: Connect4 = MIDDLE.parse().unwrap();
+    group.bench_function("connect4", |b| {
+        b.iter_batched_ref(
+            || state.clone(),
+            |state| state.apply_action(black_box(&action)).unwrap(),
+            BatchSize::SmallInput,
+        )
+    });
+    let state: Bitboard = MIDDLE.parse().unwrap();
+    group.bench_function("bitboard", |b| {
+        b.iter_batched_ref(
+            || state.clone(),
+            |state| state.apply_action(black_box(&action)).unwrap(),
+            BatchSize::SmallInput,
+        )
+    });
+    group.finish();
+}
+
+fn status(c: &mut Criterion) {
+    let mut group = c.benchmark_group("status");
+    let state: Connect4 = MIDDLE.parse().unwrap();
+    group.bench_function("connect4", |b| b.iter(|| black_box(&state).status()));
+    let state: Bitboard = MIDDLE.parse().unwrap();
+    group.bench_function("bitboard", |b| b.iter(|| black_box(&state).status()));
+    group.finish();
+}
+
+/// The baselines, the same benchmarks with `Connect4` scanning the whole
+/// board for the status after every action like it used to.
+#[cfg(feature = "bench")]
+fn full_scan(c: &mut Criterion) {
+    let mut group = c.benchmark_group("full_scan");
+    let mut rng = StdRng::seed_from_u64(1);
+    group.bench_function("playout", |b| {
+        b.iter(|| {
+            let mut state = Connect4::default();
+            loop {
+                let action = state.random_action(&mut rng).unwrap();
+                let status = state.apply_action_scanning(&action).unwrap();
+                if let GameStatus::Over { .. } = status {
+                    return status;
+                }
+            }
+        })
+    });
+    let action = Action { column: 3 };
+    let state: Connect4 = MIDDLE.parse().unwrap();
+    group.bench_function("apply_action", |b| {
+        b.iter_batched_ref(
+            || state.clone(),
+            |state| state.apply_action_scanning(black_box(&action)).unwrap(),
+            BatchSize::SmallInput,
+        )
+    });
+    group.bench_function("status", |b| b.iter(|| black_box(&state).scan_status()));
+    group.finish();
+}
+
+#[cfg(feature = "bench")]
+criterion_group!(benches, playouts, apply_action, status, full_scan);
+#[cfg(not(feature = "bench"))]
+criterion_group!(benches, playouts, apply_action, status);
+criterion_main!(benches);
@@ -0,0 +1,91 @@
+//! Looks one move ahead. Takes a win if it has one, otherwise plays a random
+//! action that doesn't hand the opponent a win.
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+
+use super::Agent;
+use crate::games::{GameResult, GameState, GameStatus};
+
+pub struct Greedy {
+    rng: StdRng,
+}
+
+impl Greedy {
+    pub fn new(seed: Option<u64>) -> Self {
+        Self {
+            rng: super::rng(seed),
+        }
+    }
+}
+
+/// The state after `action`, and whether it won the game for `player`.
+fn play<S: GameState>(state: &S, action: &S::Action, player: usize) -> (S, bool) {
+    let mut next = state.clone();
+    let status = next.apply_action(action).unwrap();
+    let won = matches!(
+        status,
+        GameStatus::Over {
+            result: GameResult::Winner { winning_player, .. },
+        } if winning_player == player
+    );
+    (next, won)
+}
+
+/// Whether the player to move in `state` can win right away.
+fn can_win<S: GameState>(state: &S) -> bool {
+    let player = super::next_player(state);
+    state
+        .legal_actions()
+        .iter()
+        .any(|action| play(state, action, player).1)
+}
+
+impl<S: GameState> Agent<S> for Greedy {
+    fn choose(&mut self, state: &S) -> S::Action {
+        let player = super::next_player(state);
+        let mut actions = state.legal_actions();
+
+        let mut safe = vec![];
+        for (i, action) in actions.iter().enumerate() {
+            let (next, won) = play(state, action, player);
+            if won {
+                return actions.swap_remove(i);
+            }
+            let over = matches!(next.status(), GameStatus::Over { .. });
+            if over || !can_win(&next) {
+                safe.push(i);
+            }
+        }
+
+        match safe.choose(&mut self.rng) {
+            Some(&i) => actions.swap_remove(i),
+            // If every action loses play any of them.
+            None => state.random_action(&mut self.rng).unwrap(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::connect4::{Action, Connect4};
+
+    #[test]
+    fn takes_a_win() {
+        let state: Connect4 = "121212".parse().unwrap();
+        let action: Action = Greedy::new(Some(1)).choose(&state);
+        assert_eq!(action.column, 0);
+    }
+
+    #[test]
+    fn doesnt_play_under_a_win() {
+        // Red has 1, 2 and 3 on the second row, blue playing 0 or 4 lets red
+        // win on top of it.
+        let state: Connect4 = "23427374".parse().unwrap();
+        for seed in 0..10 {
+            let action: Action = Greedy::new(Some(seed)).choose(&state);
+            assert!(![0, 4].contains(&action.column));
+        }
+    }
+}
@@ -0,0 +1,438 @@
+//! Monte Carlo tree search with UCT. Each iteration walks down the tree
+//! picking children by their upper confidence bound, adds one new node,
+//! plays a random game from it and scores every node on the way back up.
+//!
+//! The tree is kept between moves. When asked about a state that follows
+//! from the last one it searched, the matching subtree becomes the new root
+//! so the playouts spent on it aren't thrown away.
+//!
+//! The search can use more than one thread, see [`Parallel`].
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use super::Agent;
+use crate::games::{GameResult, GameState};
+
+/// The usual UCT exploration constant, √2.
+pub const EXPLORATION: f64 = std::f64::consts::SQRT_2;
+
+/// How long to search for each move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Budget {
+    /// Run this many iterations, split between the threads.
+    Iterations(usize),
+    /// Keep iterating until this much time has passed.
+    Time(Duration),
+}
+
+/// How a search is split between threads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Parallel {
+    /// Every thread searches its own tree and the visits of their root
+    /// children are added up. Runs the same every time with a seed.
+    Root,
+    /// The threads share one tree. Nodes on the way down count as a loss
+    /// until their playout is scored (a virtual loss) so the threads spread
+    /// out instead of all following the same path. Which thread gets the
+    /// tree first isn't up to the seed, so this doesn't repeat exactly.
+    Tree,
+}
+
+impl FromStr for Parallel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "root" => Ok(Parallel::Root),
+            "tree" => Ok(Parallel::Tree),
+            _ => Err(format!("Unknown parallel search: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for Parallel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Parallel::Root => write!(f, "root"),
+            Parallel::Tree => write!(f, "tree"),
+        }
+    }
+}
+
+/// When a thread stops searching.
+#[derive(Clone, Copy)]
+enum Stop {
+    After(usize),
+    At(Instant),
+}
+
+impl Stop {
+    fn new(budget: Budget, threads: usize) -> Self {
+        match budget {
+            Budget::Iterations(iterations) => Stop::After(iterations.div_ceil(threads)),
+            Budget::Time(time) => Stop::At(Instant::now() + time),
+        }
+    }
+
+    /// Call `iterate` until it's time to stop, always at least once so the
+    /// root has a child.
+    fn repeat(self, mut iterate: impl FnMut()) {
+        match self {
+            Stop::After(iterations) => {
+                for _ in 0..iterations.max(1) {
+                    iterate();
+                }
+            }
+            Stop::At(deadline) => loop {
+                iterate();
+                if Instant::now() >= deadline {
+                    break;
+                }
+            },
+        }
+    }
+}
+
+struct Node<A> {
+    /// The action that led here. Only the root doesn't have one.
+    action: Option<A>,
+    /// The player who took `action`, `score` is from their side.
+    player: usize,
+    children: Vec<usize>,
+    /// Legal actions that don't have a child yet.
+    untried: Vec<A>,
+    /// Counted on the way down, so it includes playouts still running.
+    visits: u32,
+    /// Total playout score, 1 for a win, ½ for a tie.
+    score: f64,
+}
+
+impl<A> Node<A> {
+    fn new<S: GameState<Action = A>>(action: Option<A>, player: usize, state: &S) -> Self {
+        Self {
+            action,
+            player,
+            children: vec![],
+            untried: state.legal_actions(),
+            visits: 0,
+            score: 0.0,
+        }
+    }
+}
+
+/// A search tree, stored as an arena with the root at index 0.
+struct Tree<S: GameState> {
+    state: S,
+    nodes: Vec<Node<S::Action>>,
+}
+
+impl<S: GameState> Tree<S> {
+    fn new(state: &S) -> Self {
+        Self {
+            state: state.clone(),
+            nodes: vec![Node::new(None, 0, state)],
+        }
+    }
+
+    /// The node for `state` if it's the root, or one or two moves below it.
+    fn find(&self, state: &S) -> Option<usize> {
+        if self.state == *state {
+            return Some(0);
+        }
+        for &child in &self.nodes[0].children {
+            let mut after = self.state.clone();
+            after
+                .apply_action(self.nodes[child].action.as_ref()?)
+                .ok()?;
+            if after == *state {
+                return Some(child);
+            }
+            for &grandchild in &self.nodes[child].children {
+                let mut after = after.clone();
+                after
+                    .apply_action(self.nodes[grandchild].action.as_ref()?)
+                    .ok()?;
+                if after == *state {
+                    return Some(grandchild);
+                }
+            }
+        }
+        None
+    }
+
+    /// Make `root` the root, dropping every node that isn't under it.
+    fn reroot(&mut self, root: usize, state: &S) {
+        let mut old: Vec<_> = self.nodes.drain(..).map(Some).collect();
+        let mut queue = VecDeque::from([root]);
+        while let Some(i) = queue.pop_front() {
+            let mut node = old[i].take().unwrap();
+            // Nodes are numbered in the order they're queued.
+            let first = self.nodes.len() + queue.len() + 1;
+            queue.extend(&node.children);
+            node.children = (first..first + node.children.len()).collect();
+            self.nodes.push(node);
+        }
+        self.state = state.clone();
+    }
+
+    /// The child of `node` with the highest upper confidence bound.
+    fn select(&self, node: usize, exploration: f64) -> usize {
+        let ln_visits = (self.nodes[node].visits as f64).ln();
+        let uct = |child: usize| {
+            let child = &self.nodes[child];
+            let visits = child.visits as f64;
+            child.score / visits + exploration * (ln_visits / visits).sqrt()
+        };
+        let children = &self.nodes[node].children;
+        children
+            .iter()
+            .copied()
+            .max_by(|&a, &b| uct(a).total_cmp(&uct(b)))
+            .unwrap()
+    }
+
+    /// Selection and expansion. Returns the path from the root to the new
+    /// node and its state, with every node on it already visited.
+    fn descend(&mut self, exploration: f64, rng: &mut StdRng) -> (Vec<usize>, S) {
+        let mut state = self.state.clone();
+        let mut path = vec![0];
+        self.nodes[0].visits += 1;
+
+        // Selection, down to a node that isn't fully expanded.
+        let mut node = 0;
+        while self.nodes[node].untried.is_empty() && !self.nodes[node].children.is_empty() {
+            node = self.select(node, exploration);
+            self.nodes[node].visits += 1;
+            let action = self.nodes[node].action.as_ref().unwrap();
+            state.apply_action(action).unwrap();
+            path.push(node);
+        }
+
+        // Expansion, unless the game is over here.
+        let untried = &mut self.nodes[node].untried;
+        if !untried.is_empty() {
+            let action = untried.swap_remove(rng.gen_range(0..untried.len()));
+            let player = super::next_player(&state);
+            state.apply_action(&action).unwrap();
+            let child = self.nodes.len();
+            let mut new = Node::new(Some(action), player, &state);
+            new.visits = 1;
+            self.nodes.push(new);
+            self.nodes[node].children.push(child);
+            path.push(child);
+        }
+
+        (path, state)
+    }
+
+    fn backpropagate(&mut self, path: &[usize], result: GameResult) {
+        for &node in path {
+            let node = &mut self.nodes[node];
+            node.score += super::score(result, node.player);
+        }
+    }
+
+    fn search(&mut self, stop: Stop, exploration: f64, rng: &mut StdRng) {
+        stop.repeat(|| {
+            let (path, mut state) = self.descend(exploration, rng);
+            let result = super::playout(&mut state, rng);
+            self.backpropagate(&path, result);
+        });
+    }
+}
+
+pub struct Mcts<S: GameState> {
+    budget: Budget,
+    exploration: f64,
+    threads: usize,
+    parallel: Parallel,
+    rng: StdRng,
+    /// One tree per thread for root parallel searches, otherwise one.
+    trees: Vec<Tree<S>>,
+}
+
+impl<S: GameState> Mcts<S> {
+    pub fn new(budget: Budget, exploration: f64, seed: Option<u64>) -> Self {
+        Self {
+            budget,
+            exploration,
+            threads: 1,
+            parallel: Parallel::Root,
+            rng: super::rng(seed),
+            trees: vec![],
+        }
+    }
+
+    /// Search with `threads` threads, or one per CPU core if it's 0.
+    pub fn threads(mut self, threads: usize, parallel: Parallel) -> Self {
+        self.threads = threads;
+        self.parallel = parallel;
+        self
+    }
+
+    fn root_parallel(&mut self, threads: usize) {
+        let stop = Stop::new(self.budget, threads);
+        let exploration = self.exploration;
+        if let [tree] = &mut self.trees[..] {
+            tree.search(stop, exploration, &mut self.rng);
+            return;
+        }
+        let seeds: Vec<u64> = (0..threads).map(|_| self.rng.gen()).collect();
+        thread::scope(|scope| {
+            for (tree, seed) in self.trees.iter_mut().zip(seeds) {
+                scope.spawn(move || {
+                    tree.search(stop, exploration, &mut StdRng::seed_from_u64(seed));
+                });
+            }
+        });
+    }
+
+    fn tree_parallel(&mut self, threads: usize) {
+        let stop = Stop::new(self.budget, threads);
+        let exploration = self.exploration;
+        let seeds: Vec<u64> = (0..threads).map(|_| self.rng.gen()).collect();
+        let tree = Mutex::new(self.trees.pop().unwrap());
+        thread::scope(|scope| {
+            for seed in seeds {
+                let tree = &tree;
+                scope.spawn(move || {
+                    let mut rng = StdRng::seed_from_u64(seed);
+                    stop.repeat(|| {
+                        let (path, mut state) = tree.lock().unwrap().descend(exploration, &mut rng);
+                        let result = super::playout(&mut state, &mut rng);
+                        tree.lock().unwrap().backpropagate(&path, result);
+                    });
+                });
+            }
+        });
+        self.trees.push(tree.into_inner().unwrap());
+    }
+}
+
+impl<S: GameState> Agent<S> for Mcts<S> {
+    fn choose(&mut self, state: &S) -> S::Action {
+        let threads = match self.threads {
+            0 => thread::available_parallelism().map_or(1, |n| n.get()),
+            threads => threads,
+        };
+        let trees = match self.parallel {
+            Parallel::Root => threads,
+            Parallel::Tree => 1,
+        };
+
+        for tree in &mut self.trees {
+            match tree.find(state) {
+                Some(node) => tree.reroot(node, state),
+                None => *tree = Tree::new(state),
+            }
+        }
+        self.trees.truncate(trees);
+        self.trees.resize_with(trees, || Tree::new(state));
+
+        match self.parallel {
+            Parallel::Root => self.root_parallel(threads),
+            Parallel::Tree => self.tree_parallel(threads),
+        }
+
+        // Add up the visits of each action over the trees. The most visited
+        // is the one the search trusts most.
+        let mut visits: Vec<(&S::Action, u32)> = vec![];
+        for tree in &self.trees {
+            for &child in &tree.nodes[0].children {
+                let child = &tree.nodes[child];
+                let action = child.action.as_ref().unwrap();
+                match visits.iter_mut().find(|(a, _)| *a == action) {
+                    Some((_, total)) => *total += child.visits,
+                    None => visits.push((action, child.visits)),
+                }
+            }
+        }
+        let (action, _) = visits.into_iter().max_by_key(|&(_, n)| n).unwrap();
+        action.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::connect4::{Action, Connect4};
+
+    #[test]
+    fn visits_go_to_the_best_action() {
+        // Blue wins in column 0, every other action lets red block.
+        let state: Connect4 = "121212".parse().unwrap();
+        let mut mcts = Mcts::new(Budget::Iterations(1000), EXPLORATION, Some(1));
+        assert_eq!(mcts.choose(&state).column, 0);
+
+        let tree = &mcts.trees[0];
+        let (best, rest): (Vec<&Node<Action>>, Vec<&Node<Action>>) = tree.nodes[0]
+            .children
+            .iter()
+            .map(|&child| &tree.nodes[child])
+            .partition(|node| node.action.unwrap().column == 0);
+        assert_eq!(best[0].score, best[0].visits as f64);
+        let others: u32 = rest.iter().map(|node| node.visits).sum();
+        assert!(best[0].visits > others);
+    }
+
+    #[test]
+    fn reuses_the_tree() {
+        let mut mcts = Mcts::new(Budget::Iterations(500), EXPLORATION, Some(1));
+        let mut state = Connect4::default();
+        let action = mcts.choose(&state);
+        state.apply_action(&action).unwrap();
+        state.apply_action(&Action { column: 3 }).unwrap();
+
+        let node = mcts.trees[0].find(&state).unwrap();
+        let visits = mcts.trees[0].nodes[node].visits;
+        assert!(visits > 0);
+
+        mcts.choose(&state);
+        assert!(mcts.trees[0].state == state);
+        assert_eq!(mcts.trees[0].nodes[0].visits, visits + 500);
+    }
+
+    #[test]
+    fn root_parallel_repeats_with_a_seed() {
+        let state: Connect4 = "44".parse().unwrap();
+        let search = || {
+            let mut mcts = Mcts::new(Budget::Iterations(2000), EXPLORATION, Some(7))
+                .threads(4, Parallel::Root);
+            let action = mcts.choose(&state);
+            let visits: Vec<Vec<u32>> = mcts
+                .trees
+                .iter()
+                .map(|tree| tree.nodes.iter().map(|node| node.visits).collect())
+                .collect();
+            (action.column, visits)
+        };
+        let (column, visits) = search();
+        assert_eq!(visits.len(), 4);
+        assert_eq!(search(), (column, visits));
+    }
+
+    #[test]
+    fn tree_parallel_counts_every_iteration() {
+        let state: Connect4 = "44".parse().unwrap();
+        let mut mcts =
+            Mcts::new(Budget::Iterations(4000), EXPLORATION, Some(1)).threads(4, Parallel::Tree);
+        mcts.choose(&state);
+        // Every iteration went through the root and one of its children.
+        let tree = &mcts.trees[0];
+        assert_eq!(tree.nodes[0].visits, 4000);
+        let children: u32 = tree.nodes[0]
+            .children
+            .iter()
+            .map(|&child| tree.nodes[child].visits)
+            .sum();
+        assert_eq!(children, 4000);
+    }
+}
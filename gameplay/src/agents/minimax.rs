@@ -0,0 +1,58 @@
+//! Depth limited minimax with alpha-beta pruning. A [`Negamax`] search that
+//! only scores finished games, so it plays randomly among actions that
+//! neither win nor lose within `depth` moves.
+
+use rand::seq::SliceRandom;
+
+use super::Agent;
+use crate::games::GameState;
+use crate::search::Negamax;
+
+pub struct Minimax<S: GameState> {
+    search: Negamax<S, fn(&S) -> i32>,
+}
+
+impl<S: GameState> Minimax<S> {
+    pub fn new(depth: usize, seed: Option<u64>) -> Self {
+        let mut rng = super::rng(seed);
+        let unfinished: fn(&S) -> i32 = |_| 0;
+        let search = Negamax::new(unfinished, depth)
+            // Shuffled so equally good actions are picked at random.
+            .order(move |_, actions| actions.shuffle(&mut rng));
+        Self { search }
+    }
+}
+
+impl<S: GameState> Agent<S> for Minimax<S> {
+    fn choose(&mut self, state: &S) -> S::Action {
+        self.search.choose(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::games::connect4::{Action, Connect4};
+
+    #[test]
+    fn sets_up_a_double_threat() {
+        // Blue has 1 and 2 on the bottom row, playing 3 threatens both 0 and 4.
+        let state: Connect4 = "2233".parse().unwrap();
+        for seed in 0..10 {
+            let action: Action = Minimax::new(4, Some(seed)).choose(&state);
+            assert_eq!(action.column, 3);
+        }
+    }
+
+    #[test]
+    fn picks_randomly_between_equal_actions() {
+        // Nothing wins or loses within 2 moves of the start.
+        let columns: HashSet<usize> = (0..20)
+            .map(|seed| Minimax::new(2, Some(seed)).choose(&Connect4::default()))
+            .map(|action: Action| action.column)
+            .collect();
+        assert!(columns.len() > 1);
+    }
+}
@@ -0,0 +1,390 @@
+//! Agents that run in this process instead of behind an HTTP service.
+//!
+//! The builtin agents only use the `GameState` trait, so they play any game.
+//! The exception is `solver`, which games provide through [`BuiltinGame`].
+
+use std::fmt;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use crate::games::connect4::solver::Book;
+use crate::games::connect4::{Bitboard, Connect4};
+use crate::games::{GameResult, GameState, GameStatus};
+
+pub mod greedy;
+pub mod mcts;
+pub mod minimax;
+pub mod montecarlo;
+pub mod random;
+pub mod solver;
+
+pub use greedy::Greedy;
+pub use mcts::{Budget, Mcts, Parallel};
+pub use minimax::Minimax;
+pub use montecarlo::MonteCarlo;
+pub use random::Random;
+pub use solver::SolverAgent;
+
+/// Something that picks actions. It's only asked when it's its turn, so
+/// `state` always has a next player.
+pub trait Agent<S: GameState> {
+    fn choose(&mut self, state: &S) -> S::Action;
+}
+
+impl<S: GameState, F: FnMut(&S) -> S::Action> Agent<S> for F {
+    fn choose(&mut self, state: &S) -> S::Action {
+        self(state)
+    }
+}
+
+/// A random number generator for an agent. Seeded with `seed` so games can
+/// be repeated, or from the OS if there isn't one.
+fn rng(seed: Option<u64>) -> StdRng {
+    match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    }
+}
+
+/// The player whose turn it is.
+fn next_player<S: GameState>(state: &S) -> usize {
+    match state.status() {
+        GameStatus::InProgress { next_player } => next_player,
+        GameStatus::Over { .. } => panic!("Agents are only asked for actions in games in progress"),
+    }
+}
+
+/// How good a finished game is for `player`, 1 for a win, ½ for a tie and
+/// 0 for a loss.
+fn score(result: GameResult, player: usize) -> f64 {
+    match result.winner() {
+        Some(winner) if winner == player => 1.0,
+        Some(_) => 0.0,
+        None => 0.5,
+    }
+}
+
+/// Play random actions until the game is over.
+fn playout<S: GameState>(state: &mut S, rng: &mut StdRng) -> GameResult {
+    loop {
+        match state.status() {
+            GameStatus::Over { result } => return result,
+            GameStatus::InProgress { .. } => {
+                let action = state.random_action(rng).unwrap();
+                state.apply_action(&action).unwrap();
+            }
+        }
+    }
+}
+
+/// A game the builtin agents can play. Every builtin but `solver` plays any
+/// `GameState`, a game with a solver returns it from `solver`. `'static` so
+/// the agents can be boxed.
+pub trait BuiltinGame: GameState + 'static {
+    fn solver(
+        _time: Duration,
+        _book: Option<&Path>,
+        _seed: Option<u64>,
+    ) -> Result<Box<dyn Agent<Self> + Send>, String> {
+        Err(format!("There's no solver for {}", Self::GAME))
+    }
+}
+
+/// A connect4 solver agent, with the book at `book` if there is one.
+fn solver_agent(
+    time: Duration,
+    book: Option<&Path>,
+    seed: Option<u64>,
+) -> Result<SolverAgent, String> {
+    let agent = SolverAgent::new(time, seed);
+    let Some(path) = book else {
+        return Ok(agent);
+    };
+    let book = File::open(path)
+        .and_then(|file| Book::read(BufReader::new(file)))
+        .map_err(|err| format!("Can't read book {}: {}", path.display(), err))?;
+    Ok(agent.with_book(book))
+}
+
+impl BuiltinGame for Connect4 {
+    fn solver(
+        time: Duration,
+        book: Option<&Path>,
+        seed: Option<u64>,
+    ) -> Result<Box<dyn Agent<Self> + Send>, String> {
+        Ok(Box::new(solver_agent(time, book, seed)?))
+    }
+}
+
+impl BuiltinGame for Bitboard {
+    fn solver(
+        time: Duration,
+        book: Option<&Path>,
+        seed: Option<u64>,
+    ) -> Result<Box<dyn Agent<Self> + Send>, String> {
+        Ok(Box::new(solver_agent(time, book, seed)?))
+    }
+}
+
+/// A builtin agent and its settings. Written like `minimax?depth=4&seed=1`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Builtin {
+    Random {
+        seed: Option<u64>,
+    },
+    Greedy {
+        seed: Option<u64>,
+    },
+    MonteCarlo {
+        iterations: usize,
+        seed: Option<u64>,
+    },
+    Minimax {
+        depth: usize,
+        seed: Option<u64>,
+    },
+    Mcts {
+        budget: Budget,
+        exploration: f64,
+        /// 0 for one per CPU core.
+        threads: usize,
+        parallel: Parallel,
+        seed: Option<u64>,
+    },
+    /// Only plays connect4.
+    Solver {
+        time: Duration,
+        /// A book of solved openings to read.
+        book: Option<PathBuf>,
+        seed: Option<u64>,
+    },
+}
+
+impl Builtin {
+    /// The agent, or why it can't play `S`.
+    pub fn agent<S: BuiltinGame>(&self) -> Result<Box<dyn Agent<S> + Send>, String> {
+        Ok(match *self {
+            Builtin::Random { seed } => Box::new(Random::new(seed)),
+            Builtin::Greedy { seed } => Box::new(Greedy::new(seed)),
+            Builtin::MonteCarlo { iterations, seed } => Box::new(MonteCarlo::new(iterations, seed)),
+            Builtin::Minimax { depth, seed } => Box::new(Minimax::new(depth, seed)),
+            Builtin::Mcts {
+                budget,
+                exploration,
+                threads,
+                parallel,
+                seed,
+            } => Box::new(Mcts::new(budget, exploration, seed).threads(threads, parallel)),
+            Builtin::Solver {
+                time,
+                ref book,
+                seed,
+            } => S::solver(time, book.as_deref(), seed)?,
+        })
+    }
+}
+
+/// The `key=value` settings of a builtin agent.
+struct Params<'a> {
+    params: Vec<(&'a str, &'a str)>,
+}
+
+impl<'a> Params<'a> {
+    fn parse(params: &'a str) -> Result<Self, String> {
+        let params = params
+            .split('&')
+            .filter(|param| !param.is_empty())
+            .map(|param| {
+                param
+                    .split_once('=')
+                    .ok_or_else(|| format!("Expected `key=value`, got `{}`", param))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { params })
+    }
+
+    /// Take the setting `key`, if it's there.
+    fn take<T: FromStr>(&mut self, key: &str) -> Result<Option<T>, String> {
+        let Some(i) = self.params.iter().position(|(k, _)| *k == key) else {
+            return Ok(None);
+        };
+        let (_, value) = self.params.remove(i);
+        value
+            .parse()
+            .map(Some)
+            .map_err(|_| format!("Invalid value for {}: `{}`", key, value))
+    }
+
+    /// Fail if there are settings nobody took.
+    fn finish(self) -> Result<(), String> {
+        match self.params.first() {
+            Some((key, _)) => Err(format!("Unknown setting `{}`", key)),
+            None => Ok(()),
+        }
+    }
+}
+
+impl FromStr for Builtin {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, params) = s.split_once('?').unwrap_or((s, ""));
+        let mut params = Params::parse(params)?;
+        let builtin = match name {
+            "random" => Builtin::Random {
+                seed: params.take("seed")?,
+            },
+            "greedy" => Builtin::Greedy {
+                seed: params.take("seed")?,
+            },
+            "montecarlo" => Builtin::MonteCarlo {
+                iterations: params.take("iterations")?.unwrap_or(1000),
+                seed: params.take("seed")?,
+            },
+            "minimax" => Builtin::Minimax {
+                depth: params.take("depth")?.unwrap_or(4),
+                seed: params.take("seed")?,
+            },
+            "mcts" => Builtin::Mcts {
+                budget: match (params.take("iterations")?, params.take("time_ms")?) {
+                    (Some(_), Some(_)) => {
+                        return Err("Give mcts `iterations` or `time_ms`, not both".to_string())
+                    }
+                    (_, Some(ms)) => Budget::Time(Duration::from_millis(ms)),
+                    (iterations, None) => Budget::Iterations(iterations.unwrap_or(1000)),
+                },
+                exploration: params.take("exploration")?.unwrap_or(mcts::EXPLORATION),
+                threads: params.take("threads")?.unwrap_or(1),
+                parallel: params.take("parallel")?.unwrap_or(Parallel::Root),
+                seed: params.take("seed")?,
+            },
+            "solver" => Builtin::Solver {
+                time: Duration::from_millis(params.take("time_ms")?.unwrap_or(1000)),
+                book: params.take("book")?,
+                seed: params.take("seed")?,
+            },
+            _ => return Err(format!("Unknown builtin agent: {}", name)),
+        };
+        params.finish()?;
+        Ok(builtin)
+    }
+}
+
+impl fmt::Display for Builtin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (name, mut params, seed) = match *self {
+            Builtin::Random { seed } => ("random", vec![], seed),
+            Builtin::Greedy { seed } => ("greedy", vec![], seed),
+            Builtin::MonteCarlo { iterations, seed } => (
+                "montecarlo",
+                vec![format!("iterations={}", iterations)],
+                seed,
+            ),
+            Builtin::Minimax { depth, seed } => ("minimax", vec![format!("depth={}", depth)], seed),
+            Builtin::Mcts {
+                budget,
+                exploration,
+                threads,
+                parallel,
+                seed,
+            } => {
+                let mut params = vec![match budget {
+                    Budget::Iterations(iterations) => format!("iterations={}", iterations),
+                    Budget::Time(time) => format!("time_ms={}", time.as_millis()),
+                }];
+                if exploration != mcts::EXPLORATION {
+                    params.push(format!("exploration={}", exploration));
+                }
+                if threads != 1 {
+                    params.push(format!("threads={}", threads));
+                }
+                if parallel != Parallel::Root {
+                    params.push(format!("parallel={}", parallel));
+                }
+                ("mcts", params, seed)
+            }
+            Builtin::Solver {
+                time,
+                ref book,
+                seed,
+            } => {
+                let mut params = vec![format!("time_ms={}", time.as_millis())];
+                params.extend(book.as_ref().map(|book| format!("book={}", book.display())));
+                ("solver", params, seed)
+            }
+        };
+        params.extend(seed.map(|seed| format!("seed={}", seed)));
+        write!(f, "{}", name)?;
+        if !params.is_empty() {
+            write!(f, "?{}", params.join("&"))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_builtin() {
+        assert_eq!(
+            "minimax?depth=2&seed=7".parse(),
+            Ok(Builtin::Minimax {
+                depth: 2,
+                seed: Some(7)
+            })
+        );
+        assert_eq!(
+            "montecarlo".parse(),
+            Ok(Builtin::MonteCarlo {
+                iterations: 1000,
+                seed: None
+            })
+        );
+        assert_eq!(
+            "mcts?time_ms=200&exploration=0.7".parse(),
+            Ok(Builtin::Mcts {
+                budget: Budget::Time(Duration::from_millis(200)),
+                exploration: 0.7,
+                threads: 1,
+                parallel: Parallel::Root,
+                seed: None
+            })
+        );
+        assert!("mcts?iterations=10&time_ms=10".parse::<Builtin>().is_err());
+        assert!("minimax?depth=deep".parse::<Builtin>().is_err());
+        assert!("minimax?width=2".parse::<Builtin>().is_err());
+        assert!("alphazero".parse::<Builtin>().is_err());
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for spec in [
+            "random",
+            "greedy?seed=3",
+            "montecarlo?iterations=50",
+            "minimax?depth=2&seed=1",
+            "mcts?iterations=1000",
+            "mcts?time_ms=500&exploration=0.5&seed=2",
+            "mcts?iterations=8000&threads=0&parallel=tree",
+            "solver?time_ms=1000",
+            "solver?time_ms=500&book=openings.txt&seed=4",
+        ] {
+            let builtin: Builtin = spec.parse().unwrap();
+            assert_eq!(builtin.to_string(), spec);
+        }
+    }
+
+    #[test]
+    fn solver_book_has_to_be_readable() {
+        let builtin: Builtin = "solver?book=/nonexistent/book.txt".parse().unwrap();
+        assert!(builtin.agent::<Connect4>().is_err());
+    }
+}
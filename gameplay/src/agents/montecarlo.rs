@@ -0,0 +1,61 @@
+//! Flat Monte Carlo. Splits its playouts evenly between the legal actions
+//! and plays the one that scored best.
+
+use rand::rngs::StdRng;
+
+use super::Agent;
+use crate::games::GameState;
+
+pub struct MonteCarlo {
+    /// Random playouts per move.
+    iterations: usize,
+    rng: StdRng,
+}
+
+impl MonteCarlo {
+    pub fn new(iterations: usize, seed: Option<u64>) -> Self {
+        Self {
+            iterations,
+            rng: super::rng(seed),
+        }
+    }
+}
+
+impl<S: GameState> Agent<S> for MonteCarlo {
+    fn choose(&mut self, state: &S) -> S::Action {
+        let player = super::next_player(state);
+        let mut actions = state.legal_actions();
+        let playouts = (self.iterations / actions.len()).max(1);
+
+        let mut best = 0;
+        let mut best_score = f64::NEG_INFINITY;
+        for (i, action) in actions.iter().enumerate() {
+            let mut after = state.clone();
+            after.apply_action(action).unwrap();
+            let mut score = 0.0;
+            for _ in 0..playouts {
+                let result = super::playout(&mut after.clone(), &mut self.rng);
+                score += super::score(result, player);
+            }
+            if score > best_score {
+                best = i;
+                best_score = score;
+            }
+        }
+        actions.swap_remove(best)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::connect4::{Action, Connect4};
+
+    #[test]
+    fn prefers_the_middle() {
+        // No action wins or loses right away, but random games started in
+        // the middle column are won more often.
+        let action: Action = MonteCarlo::new(7000, Some(1)).choose(&Connect4::default());
+        assert_eq!(action.column, 3);
+    }
+}
@@ -0,0 +1,26 @@
+//! Plays a random legal action.
+
+use rand::rngs::StdRng;
+
+use super::Agent;
+use crate::games::GameState;
+
+pub struct Random {
+    rng: StdRng,
+}
+
+impl Random {
+    pub fn new(seed: Option<u64>) -> Self {
+        Self {
+            rng: super::rng(seed),
+        }
+    }
+}
+
+impl<S: GameState> Agent<S> for Random {
+    fn choose(&mut self, state: &S) -> S::Action {
+        state
+            .random_action(&mut self.rng)
+            .expect("agents only choose in games in progress")
+    }
+}
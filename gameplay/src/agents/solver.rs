@@ -0,0 +1,61 @@
+//! Connect4 only. Spends up to half its time solving the position and plays
+//! the best action if it can. Positions near the start of the game take too
+//! long to solve, for those it searches with MCTS for the other half.
+
+use std::time::Duration;
+
+use super::mcts::{Budget, Mcts, EXPLORATION};
+use super::Agent;
+use crate::games::connect4::solver::{self, Book, Solver};
+use crate::games::connect4::{Action, Bitboard, Connect4};
+
+pub struct SolverAgent {
+    time: Duration,
+    solver: Solver,
+    mcts: Mcts<Bitboard>,
+}
+
+impl SolverAgent {
+    pub fn new(time: Duration, seed: Option<u64>) -> Self {
+        Self {
+            time,
+            solver: Solver::new(),
+            mcts: Mcts::new(Budget::Time(time / 2), EXPLORATION, seed),
+        }
+    }
+
+    /// Look up positions in `book` instead of searching them.
+    pub fn with_book(mut self, book: Book) -> Self {
+        self.solver = self.solver.with_book(book);
+        self
+    }
+}
+
+impl Agent<Bitboard> for SolverAgent {
+    fn choose(&mut self, state: &Bitboard) -> Action {
+        match self.solver.analyze_within(state, self.time / 2) {
+            Some(values) => solver::best(&values),
+            None => self.mcts.choose(state),
+        }
+    }
+}
+
+impl Agent<Connect4> for SolverAgent {
+    fn choose(&mut self, state: &Connect4) -> Action {
+        let state = Bitboard::try_from(state.clone()).expect("Connect4 states are valid boards");
+        self.choose(&state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::GameState;
+
+    #[test]
+    fn plays_before_it_can_solve() {
+        let mut agent = SolverAgent::new(Duration::from_millis(50), Some(1));
+        let state = Bitboard::default();
+        assert!(state.valid_action(&agent.choose(&state)));
+    }
+}
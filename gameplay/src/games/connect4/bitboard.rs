@@ -0,0 +1,306 @@
+//! Connect4 stored as bitboards, for agents that play a lot of games.
+//!
+//! Each player's chips are a `u64` with the bit `col * 7 + row` set for
+//! every chip they have. Columns get 7 bits for 6 rows so there's always an
+//! empty bit above the top row, and a line of four shows up as four set bits
+//! spaced 1 (vertical), 7 (horizontal), 6 or 8 (diagonal) apart. Playing an
+//! action and checking for a win only take a few shifts.
+//!
+//! [`Bitboard`] serializes the same as [`Connect4`], so agents can take
+//! either one over the wire.
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use super::{Action, Connect4, Error, COLS, ROWS};
+use crate::games::{Game, GameResult, GameState, GameStatus, Line};
+
+/// Bits per column, one more than there are rows.
+pub(crate) const HEIGHT: usize = ROWS + 1;
+
+/// Every cell on the board.
+pub(crate) const FULL: u64 = {
+    let mut full = 0;
+    let mut col = 0;
+    while col < COLS {
+        full |= ((1 << ROWS) - 1) << (col * HEIGHT);
+        col += 1;
+    }
+    full
+};
+
+fn bit(col: usize, row: usize) -> u64 {
+    1 << (col * HEIGHT + row)
+}
+
+/// Whether `chips` has four in a row.
+fn has_four(chips: u64) -> bool {
+    [1, HEIGHT, HEIGHT - 1, HEIGHT + 1]
+        .into_iter()
+        .any(|shift| {
+            let pairs = chips & (chips >> shift);
+            pairs & (pairs >> (2 * shift)) != 0
+        })
+}
+
+/// The line of four in `chips` that [`Connect4`] would report, it looks
+/// for vertical lines first, then horizontal and then diagonal.
+fn winning_line(chips: u64) -> Option<Line> {
+    let cell = |bit: usize| (bit / HEIGHT, bit % HEIGHT);
+    [1, HEIGHT, HEIGHT + 1, HEIGHT - 1]
+        .into_iter()
+        .find_map(|shift| {
+            let pairs = chips & (chips >> shift);
+            let starts = pairs & (pairs >> (2 * shift));
+            let from = (0..64)
+                .filter(|bit| starts & (1 << bit) != 0)
+                .map(cell)
+                // Connect4 goes through horizontal lines a row at a time.
+                .min_by_key(|&(col, row)| {
+                    if shift == HEIGHT {
+                        (row, col)
+                    } else {
+                        (col, row)
+                    }
+                })?;
+            let to = cell(from.0 * HEIGHT + from.1 + 3 * shift);
+            Some(Line { from, to })
+        })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "Connect4", into = "Connect4")]
+pub struct Bitboard {
+    /// Chips of each player.
+    chips: [u64; 2],
+    /// How many chips are in each column.
+    heights: [u8; COLS],
+    next_player: usize,
+}
+
+impl Bitboard {
+    pub fn get(&self, col: usize, row: usize) -> Option<usize> {
+        (0..2).find(|&player| self.chips[player] & bit(col, row) != 0)
+    }
+
+    /// The chips of `player`.
+    pub fn chips(&self, player: usize) -> u64 {
+        self.chips[player]
+    }
+
+    /// Every chip on the board.
+    pub fn mask(&self) -> u64 {
+        self.chips[0] | self.chips[1]
+    }
+}
+
+impl Default for Bitboard {
+    fn default() -> Self {
+        Self {
+            chips: [0; 2],
+            heights: [0; COLS],
+            next_player: 0,
+        }
+    }
+}
+
+impl GameState for Bitboard {
+    type Error = Error;
+    type Action = Action;
+
+    const GAME: Game = Game::Connect4;
+
+    fn valid_action(&self, action: &Self::Action) -> bool {
+        action.column < COLS && (self.heights[action.column] as usize) < ROWS
+    }
+
+    fn legal_actions(&self) -> Vec<Self::Action> {
+        if let GameStatus::Over { .. } = self.status() {
+            return vec![];
+        }
+        (0..COLS)
+            .map(|column| Action { column })
+            .filter(|action| self.valid_action(action))
+            .collect()
+    }
+
+    fn legal_action_count(&self) -> usize {
+        if let GameStatus::Over { .. } = self.status() {
+            return 0;
+        }
+        self.heights
+            .iter()
+            .filter(|&&height| (height as usize) < ROWS)
+            .count()
+    }
+
+    // Picks a column without collecting the legal actions, playouts call
+    // this for every move.
+    fn random_action<R: Rng + ?Sized>(&self, rng: &mut R) -> Option<Self::Action> {
+        let count = self.legal_action_count();
+        if count == 0 {
+            return None;
+        }
+        let n = rng.gen_range(0..count);
+        (0..COLS)
+            .filter(|&column| (self.heights[column] as usize) < ROWS)
+            .nth(n)
+            .map(|column| Action { column })
+    }
+
+    fn status(&self) -> GameStatus {
+        if let Some(winning_player) = (0..2).find(|&player| has_four(self.chips[player])) {
+            return GameStatus::Over {
+                result: GameResult::Winner {
+                    winning_player,
+                    line: winning_line(self.chips[winning_player]),
+                },
+            };
+        }
+        if self.mask() == FULL {
+            return GameStatus::Over {
+                result: GameResult::Tie,
+            };
+        }
+        GameStatus::InProgress {
+            next_player: self.next_player,
+        }
+    }
+
+    fn apply_action(&mut self, action: &Self::Action) -> Result<GameStatus, Self::Error> {
+        if action.column >= COLS {
+            return Err(Error::UnknownColumn(action.column));
+        }
+        let height = &mut self.heights[action.column];
+        if *height as usize >= ROWS {
+            return Err(Error::FullColumn(action.column));
+        }
+        self.chips[self.next_player] |= bit(action.column, *height as usize);
+        *height += 1;
+        self.next_player = 1 - self.next_player;
+        Ok(self.status())
+    }
+
+    fn unapply_action(&mut self, action: &Self::Action) -> Result<(), Self::Error> {
+        if action.column >= COLS {
+            return Err(Error::UnknownColumn(action.column));
+        }
+        let last_player = 1 - self.next_player;
+        let height = &mut self.heights[action.column];
+        if *height == 0 {
+            return Err(Error::NotLastAction(action.column));
+        }
+        let top = bit(action.column, *height as usize - 1);
+        if self.chips[last_player] & top == 0 {
+            return Err(Error::NotLastAction(action.column));
+        }
+        self.chips[last_player] &= !top;
+        *height -= 1;
+        self.next_player = last_player;
+        Ok(())
+    }
+}
+
+impl TryFrom<Connect4> for Bitboard {
+    type Error = Error;
+
+    fn try_from(state: Connect4) -> Result<Self, Self::Error> {
+        state.validate()?;
+        let mut bitboard = Bitboard {
+            next_player: state.next_player,
+            ..Default::default()
+        };
+        for col in 0..COLS {
+            for row in 0..ROWS {
+                if let Some(player) = state.get(col, row) {
+                    bitboard.chips[player] |= bit(col, row);
+                    bitboard.heights[col] += 1;
+                }
+            }
+        }
+        Ok(bitboard)
+    }
+}
+
+impl From<Bitboard> for Connect4 {
+    fn from(bitboard: Bitboard) -> Self {
+        let mut state = Connect4 {
+            next_player: bitboard.next_player,
+            ..Default::default()
+        };
+        for col in 0..COLS {
+            for row in 0..ROWS {
+                state.set(col, row, bitboard.get(col, row));
+            }
+        }
+        state.refresh();
+        state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    #[test]
+    fn plays_the_same_as_connect4() {
+        let mut rng = StdRng::seed_from_u64(1);
+        for _ in 0..200 {
+            let mut state = Connect4::default();
+            let mut bitboard = Bitboard::default();
+            while let GameStatus::InProgress { .. } = state.status() {
+                assert_eq!(bitboard.legal_action_count(), state.legal_action_count());
+                assert!(bitboard.valid_action(&bitboard.random_action(&mut rng).unwrap()));
+                let action = state.random_action(&mut rng).unwrap();
+                assert_eq!(
+                    state.apply_action(&action).unwrap(),
+                    bitboard.apply_action(&action).unwrap()
+                );
+                assert_eq!(Connect4::from(bitboard.clone()), state);
+                assert_eq!(bitboard.legal_actions(), state.legal_actions());
+            }
+            assert_eq!(bitboard.legal_action_count(), 0);
+            assert_eq!(bitboard.random_action(&mut rng), None);
+
+            // Undoing every action gets back to the start.
+            for action in state.history().to_vec().iter().rev() {
+                state.unapply_action(action).unwrap();
+                bitboard.unapply_action(action).unwrap();
+                assert_eq!(Connect4::from(bitboard.clone()), state);
+            }
+            assert_eq!(bitboard, Bitboard::default());
+        }
+    }
+
+    #[test]
+    fn serializes_like_connect4() {
+        let mut state = Connect4::default();
+        for column in [3, 3, 4, 2, 6] {
+            state.apply_action(&Action { column }).unwrap();
+        }
+        let json = serde_json::to_string(&state).unwrap();
+        let bitboard: Bitboard = serde_json::from_str(&json).unwrap();
+        assert_eq!(serde_json::to_string(&bitboard).unwrap(), json);
+    }
+
+    #[test]
+    fn rejects_boards_it_cant_hold() {
+        let mut board = vec![None; ROWS * COLS];
+        board[1] = Some(0);
+        let floating = Connect4 {
+            board,
+            next_player: 1,
+            ..Default::default()
+        };
+        assert!(matches!(
+            Bitboard::try_from(floating),
+            Err(Error::InvalidBoard(_))
+        ));
+
+        let short = r#"{"board":[null,null],"next_player":0}"#;
+        assert!(serde_json::from_str::<Bitboard>(short).is_err());
+    }
+}
@@ -0,0 +1,601 @@
+use std::hash::{Hash, Hasher};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::games::{Game, GameResult, GameState, GameStatus, Line};
+
+pub mod bitboard;
+pub mod notation;
+pub mod solver;
+
+pub use bitboard::Bitboard;
+pub use notation::Moves;
+
+pub const ROWS: usize = 6;
+pub const COLS: usize = 7;
+
+/// `player` won with four from `from` to `to`.
+fn won(player: usize, from: (usize, usize), to: (usize, usize)) -> GameStatus {
+    GameStatus::Over {
+        result: GameResult::Winner {
+            winning_player: player,
+            line: Some(Line { from, to }),
+        },
+    }
+}
+
+fn check4(a: Option<usize>, b: Option<usize>, c: Option<usize>, d: Option<usize>) -> Option<usize> {
+    match (a, b, c, d) {
+        (Some(i), Some(j), Some(k), Some(l)) if i == j && j == k && k == l => Some(i),
+        _ => None,
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Column must be between 0 and 6. Got `{0}`.")]
+    UnknownColumn(usize),
+    #[error("Column `{0}` is full.")]
+    FullColumn(usize),
+    #[error("Invalid board: {0}")]
+    InvalidBoard(String),
+    #[error("Invalid moves: {0}")]
+    InvalidMoves(String),
+    #[error("Column `{0}` doesn't have the last chip played.")]
+    NotLastAction(usize),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub struct Action {
+    pub column: usize,
+}
+
+/// A Connect4 game. States read from json are checked to be boards a real
+/// game could reach.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(try_from = "Unchecked")]
+pub struct Connect4 {
+    board: Vec<Option<usize>>,
+    next_player: usize,
+    /// Actions applied to this state, oldest first.
+    #[serde(skip)]
+    history: Vec<Action>,
+    /// Chips on the board.
+    #[serde(skip)]
+    moves: usize,
+    /// Kept up to date as actions are applied and undone, so it doesn't
+    /// have to be worked out from the whole board every time.
+    #[serde(skip)]
+    status: GameStatus,
+}
+
+impl Connect4 {
+    pub fn get(&self, col: usize, row: usize) -> Option<usize> {
+        self.board[col * ROWS + row]
+    }
+
+    /// The actions applied to this state, oldest first. A state read from
+    /// json doesn't know how it was reached, its history starts there.
+    pub fn history(&self) -> &[Action] {
+        &self.history
+    }
+
+    /// Check the board could have come from a real game: the right size,
+    /// only chips of players 0 and 1, no chips above empty cells and the
+    /// turn of whoever has played fewer chips.
+    fn validate(&self) -> Result<(), Error> {
+        if self.board.len() != ROWS * COLS {
+            return Err(Error::InvalidBoard(format!(
+                "expected {} cells, got {}",
+                ROWS * COLS,
+                self.board.len()
+            )));
+        }
+        let mut chips = [0, 0];
+        for col in 0..COLS {
+            for row in 0..ROWS {
+                match self.get(col, row) {
+                    Some(player) if player > 1 => {
+                        return Err(Error::InvalidBoard(format!(
+                            "unknown player {} in column {} row {}",
+                            player, col, row
+                        )))
+                    }
+                    Some(_) if row > 0 && self.get(col, row - 1).is_none() => {
+                        return Err(Error::InvalidBoard(format!(
+                            "chip in column {} row {} is floating",
+                            col, row
+                        )))
+                    }
+                    Some(player) => chips[player] += 1,
+                    None => {}
+                }
+            }
+        }
+        // Player 0 goes first, so they have as many chips as player 1 or
+        // one more.
+        let next_player = if chips[0] == chips[1] {
+            0
+        } else if chips[0] == chips[1] + 1 {
+            1
+        } else {
+            return Err(Error::InvalidBoard(format!(
+                "player 0 has {} chips and player 1 has {}",
+                chips[0], chips[1]
+            )));
+        };
+        if self.next_player != next_player {
+            return Err(Error::InvalidBoard(format!(
+                "next player is {} but it's player {}'s turn",
+                self.next_player, next_player
+            )));
+        }
+        Ok(())
+    }
+
+    /// Take back the last action in the history and return it.
+    pub fn undo(&mut self) -> Option<Action> {
+        let action = *self.history.last()?;
+        self.unapply_action(&action)
+            .expect("the history is the actions applied");
+        Some(action)
+    }
+
+    fn set(&mut self, col: usize, row: usize, val: Option<usize>) {
+        self.board[col * ROWS + row] = val;
+    }
+
+    /// Recount the chips and work out the status again, after the board was
+    /// set some other way than playing actions.
+    fn refresh(&mut self) {
+        self.moves = self.board.iter().flatten().count();
+        self.status = self.scan();
+    }
+
+    /// Work out the status from the whole board.
+    #[allow(clippy::identity_op)]
+    fn scan(&self) -> GameStatus {
+        // Check vertical wins
+        for col in 0..COLS {
+            for row in 0..3 {
+                if let Some(player) = check4(
+                    self.get(col, row + 0),
+                    self.get(col, row + 1),
+                    self.get(col, row + 2),
+                    self.get(col, row + 3),
+                ) {
+                    return won(player, (col, row), (col, row + 3));
+                }
+            }
+        }
+
+        // Check horizontal wins
+        for row in 0..ROWS {
+            for col in 0..4 {
+                if let Some(player) = check4(
+                    self.get(col + 0, row),
+                    self.get(col + 1, row),
+                    self.get(col + 2, row),
+                    self.get(col + 3, row),
+                ) {
+                    return won(player, (col, row), (col + 3, row));
+                }
+            }
+        }
+
+        // Check diagonal up wins
+        for col in 0..4 {
+            for row in 0..3 {
+                if let Some(player) = check4(
+                    self.get(col + 0, row + 0),
+                    self.get(col + 1, row + 1),
+                    self.get(col + 2, row + 2),
+                    self.get(col + 3, row + 3),
+                ) {
+                    return won(player, (col, row), (col + 3, row + 3));
+                }
+            }
+        }
+
+        // Check diagonal down wins
+        for col in 0..4 {
+            for row in 3..6 {
+                if let Some(player) = check4(
+                    self.get(col + 0, row - 0),
+                    self.get(col + 1, row - 1),
+                    self.get(col + 2, row - 2),
+                    self.get(col + 3, row - 3),
+                ) {
+                    return won(player, (col, row), (col + 3, row - 3));
+                }
+            }
+        }
+
+        // Check for tie
+        for col in 0..COLS {
+            if self.get(col, ROWS - 1).is_none() {
+                return GameStatus::InProgress {
+                    next_player: self.next_player,
+                };
+            }
+        }
+
+        GameStatus::Over {
+            result: GameResult::Tie,
+        }
+    }
+
+    /// The status after a chip was played at `col`, `row` in a game that
+    /// wasn't over. Only lines through that chip can have won it.
+    fn status_after(&self, col: usize, row: usize) -> GameStatus {
+        let player = self.get(col, row);
+        let same = |c: isize, r: isize| {
+            (0..COLS as isize).contains(&c)
+                && (0..ROWS as isize).contains(&r)
+                && self.get(c as usize, r as usize) == player
+        };
+        let (col, row) = (col as isize, row as isize);
+        // In the same order as `scan`, so both find the same line.
+        for (dc, dr) in [(0, 1), (1, 0), (1, 1), (1, -1)] {
+            let back = (1..4)
+                .take_while(|i| same(col - i * dc, row - i * dr))
+                .count() as isize;
+            let ahead = (1..4)
+                .take_while(|i| same(col + i * dc, row + i * dr))
+                .count() as isize;
+            if back + ahead >= 3 {
+                let (c, r) = (col - back * dc, row - back * dr);
+                return won(
+                    player.unwrap(),
+                    (c as usize, r as usize),
+                    ((c + 3 * dc) as usize, (r + 3 * dr) as usize),
+                );
+            }
+        }
+        if self.moves == ROWS * COLS {
+            return GameStatus::Over {
+                result: GameResult::Tie,
+            };
+        }
+        GameStatus::InProgress {
+            next_player: self.next_player,
+        }
+    }
+}
+
+impl Default for Connect4 {
+    fn default() -> Self {
+        Self {
+            board: vec![None; ROWS * COLS],
+            next_player: 0,
+            history: vec![],
+            moves: 0,
+            status: GameStatus::InProgress { next_player: 0 },
+        }
+    }
+}
+
+/// How a [`Connect4`] is read from json, before it's been checked.
+#[derive(Deserialize)]
+struct Unchecked {
+    board: Vec<Option<usize>>,
+    next_player: usize,
+}
+
+impl TryFrom<Unchecked> for Connect4 {
+    type Error = Error;
+
+    fn try_from(unchecked: Unchecked) -> Result<Self, Self::Error> {
+        let mut state = Connect4 {
+            board: unchecked.board,
+            next_player: unchecked.next_player,
+            ..Default::default()
+        };
+        state.validate()?;
+        state.refresh();
+        Ok(state)
+    }
+}
+
+// States are the same position however they were reached, so the history
+// is left out.
+impl PartialEq for Connect4 {
+    fn eq(&self, other: &Self) -> bool {
+        self.board == other.board && self.next_player == other.next_player
+    }
+}
+
+impl Eq for Connect4 {}
+
+impl Hash for Connect4 {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.board.hash(state);
+        self.next_player.hash(state);
+    }
+}
+
+/// Working out the status from the whole board after every action, the way
+/// `Connect4` used to. Only for the benchmarks to compare against.
+#[cfg(feature = "bench")]
+impl Connect4 {
+    /// `status`, by scanning the whole board.
+    pub fn scan_status(&self) -> GameStatus {
+        self.scan()
+    }
+
+    /// `apply_action`, scanning the whole board for the status.
+    pub fn apply_action_scanning(&mut self, action: &Action) -> Result<GameStatus, Error> {
+        if action.column >= COLS {
+            return Err(Error::UnknownColumn(action.column));
+        }
+        for row in 0..ROWS {
+            if self.get(action.column, row).is_none() {
+                self.set(action.column, row, Some(self.next_player));
+                self.next_player = (self.next_player + 1) % 2;
+                self.moves += 1;
+                self.history.push(*action);
+                self.status = self.scan();
+                return Ok(self.status);
+            }
+        }
+        Err(Error::FullColumn(action.column))
+    }
+}
+
+impl GameState for Connect4 {
+    type Error = Error;
+    type Action = Action;
+
+    const GAME: Game = Game::Connect4;
+
+    fn valid_action(&self, action: &Self::Action) -> bool {
+        if action.column >= COLS {
+            return false;
+        }
+        self.get(action.column, ROWS - 1).is_none()
+    }
+
+    fn legal_actions(&self) -> Vec<Self::Action> {
+        if let GameStatus::Over { .. } = self.status() {
+            return vec![];
+        }
+        (0..COLS)
+            .map(|column| Action { column })
+            .filter(|action| self.valid_action(action))
+            .collect()
+    }
+
+    fn status(&self) -> GameStatus {
+        self.status
+    }
+
+    fn apply_action(&mut self, action: &Self::Action) -> Result<GameStatus, Self::Error> {
+        if action.column >= COLS {
+            return Err(Error::UnknownColumn(action.column));
+        }
+        for row in 0..ROWS {
+            if self.get(action.column, row).is_none() {
+                self.set(action.column, row, Some(self.next_player));
+                self.next_player = (self.next_player + 1) % 2;
+                self.moves += 1;
+                self.history.push(*action);
+                self.status = match self.status {
+                    GameStatus::InProgress { .. } => self.status_after(action.column, row),
+                    // Playing on after the game is over, there might be more
+                    // than one line of four.
+                    GameStatus::Over { .. } => self.scan(),
+                };
+                return Ok(self.status);
+            }
+        }
+        Err(Error::FullColumn(action.column))
+    }
+
+    fn unapply_action(&mut self, action: &Self::Action) -> Result<(), Self::Error> {
+        if action.column >= COLS {
+            return Err(Error::UnknownColumn(action.column));
+        }
+        let last_player = 1 - self.next_player;
+        let top = (0..ROWS)
+            .rev()
+            .find(|&row| self.get(action.column, row).is_some())
+            .filter(|&row| self.get(action.column, row) == Some(last_player));
+        let played_last = self.history.last().is_none_or(|last| last == action);
+        match top {
+            Some(row) if played_last => {
+                self.set(action.column, row, None);
+                self.next_player = last_player;
+                self.moves -= 1;
+                self.history.pop();
+                self.status = match self.status {
+                    GameStatus::InProgress { .. } => GameStatus::InProgress {
+                        next_player: last_player,
+                    },
+                    GameStatus::Over { .. } => self.scan(),
+                };
+                Ok(())
+            }
+            _ => Err(Error::NotLastAction(action.column)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn check_serialized() {
+        let game = super::Connect4::default();
+        eprintln!("{}", serde_json::to_string(&game).unwrap());
+        let action = super::Action { column: 3 };
+        eprintln!("{}", serde_json::to_string(&action).unwrap());
+    }
+
+    #[test]
+    fn rejects_invalid_json() {
+        use super::*;
+
+        let json = |board: &[Option<usize>], next_player: usize| {
+            serde_json::json!({ "board": board, "next_player": next_player }).to_string()
+        };
+        let read = |json: &str| serde_json::from_str::<Connect4>(json);
+
+        let mut board = vec![None; ROWS * COLS];
+        board[0] = Some(0);
+        let state = read(&json(&board, 1)).unwrap();
+        assert_eq!(state, "1".parse().unwrap());
+        assert_eq!(
+            read(&serde_json::to_string(&state).unwrap()).unwrap(),
+            state
+        );
+
+        let err = |json: &str| read(json).unwrap_err().to_string();
+        assert!(err(&json(&board[..41], 1)).contains("expected 42 cells, got 41"));
+        assert!(err(&json(&board, 0)).contains("next player is 0 but it's player 1's turn"));
+        board[0] = Some(2);
+        assert!(err(&json(&board, 1)).contains("unknown player 2 in column 0 row 0"));
+        board[0] = None;
+        board[1] = Some(0);
+        assert!(err(&json(&board, 1)).contains("chip in column 0 row 1 is floating"));
+        board[0] = Some(0);
+        assert!(err(&json(&board, 1)).contains("player 0 has 2 chips and player 1 has 0"));
+    }
+
+    #[test]
+    fn legal_actions() {
+        use super::*;
+        use rand::SeedableRng;
+
+        let mut game = Connect4::default();
+        assert_eq!(game.legal_actions().len(), COLS);
+        for _ in 0..ROWS {
+            game.apply_action(&Action { column: 2 }).unwrap();
+        }
+        let columns: Vec<usize> = game.legal_actions().iter().map(|a| a.column).collect();
+        assert_eq!(columns, vec![0, 1, 3, 4, 5, 6]);
+        assert_eq!(game.legal_action_count(), 6);
+        let mut rng = rand::rngs::StdRng::seed_from_u64(1);
+        for _ in 0..20 {
+            assert!(game.valid_action(&game.random_action(&mut rng).unwrap()));
+        }
+
+        // Nothing is legal once somebody has won.
+        for column in [0, 1, 0, 1, 0, 1, 0] {
+            game.apply_action(&Action { column }).unwrap();
+        }
+        assert!(game.legal_actions().is_empty());
+        assert_eq!(game.legal_action_count(), 0);
+        assert_eq!(game.random_action(&mut rng), None);
+    }
+
+    #[test]
+    fn status_has_the_winning_line() {
+        use super::*;
+
+        let diagonal_up = "
+            .......
+            .......
+            ...x...
+            ..xo...
+            .xoo...
+            xoox...";
+        let diagonal_down = "
+            .......
+            .......
+            ...x...
+            ...ox..
+            ...oox.
+            ...xoox";
+        for (position, from, to) in [
+            ("1212121", (0, 0), (0, 3)),
+            ("1122334", (0, 0), (3, 0)),
+            (diagonal_up, (0, 0), (3, 3)),
+            (diagonal_down, (3, 3), (6, 0)),
+        ] {
+            let expected = GameStatus::Over {
+                result: GameResult::Winner {
+                    winning_player: 0,
+                    line: Some(Line { from, to }),
+                },
+            };
+            assert_eq!(position.parse::<Connect4>().unwrap().status(), expected);
+            assert_eq!(position.parse::<Bitboard>().unwrap().status(), expected);
+        }
+
+        let line = Line {
+            from: (3, 3),
+            to: (6, 0),
+        };
+        let cells: Vec<_> = line.cells().collect();
+        assert_eq!(cells, vec![(3, 3), (4, 2), (5, 1), (6, 0)]);
+    }
+
+    #[test]
+    fn status_is_kept_up_to_date() {
+        use super::*;
+        use rand::rngs::StdRng;
+        use rand::{Rng, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(1);
+        for _ in 0..200 {
+            let mut game = Connect4::default();
+            while let Some(action) = game.random_action(&mut rng) {
+                game.apply_action(&action).unwrap();
+                assert_eq!(game.status(), game.scan());
+                // Take some moves back to check undoing keeps it too.
+                if rng.gen_ratio(1, 4) {
+                    game.undo();
+                    assert_eq!(game.status(), game.scan());
+                }
+            }
+            assert!(matches!(game.status(), GameStatus::Over { .. }));
+            while game.undo().is_some() {
+                assert_eq!(game.status(), game.scan());
+            }
+        }
+
+        // A full board with no four is a tie.
+        let full: Connect4 = "
+            ooxxoox
+            xxooxxo
+            ooxxoox
+            xxooxxo
+            ooxxoox
+            xxooxxo"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            full.status(),
+            GameStatus::Over {
+                result: GameResult::Tie
+            }
+        );
+    }
+
+    #[test]
+    fn undo() {
+        use super::*;
+
+        let mut game: Connect4 = "4453".parse().unwrap();
+        let columns: Vec<usize> = game.history().iter().map(|a| a.column).collect();
+        assert_eq!(columns, vec![3, 3, 4, 2]);
+
+        assert_eq!(game.undo(), Some(Action { column: 2 }));
+        assert_eq!(game, "445".parse().unwrap());
+        // Only the last chip can be taken back.
+        assert!(matches!(
+            game.unapply_action(&Action { column: 3 }),
+            Err(Error::NotLastAction(3))
+        ));
+        game.unapply_action(&Action { column: 4 }).unwrap();
+        assert_eq!(game.history().len(), 2);
+
+        // States from json start without a history but can still be undone.
+        let json = serde_json::to_string(&game).unwrap();
+        let mut read: Connect4 = serde_json::from_str(&json).unwrap();
+        assert_eq!(read, game);
+        assert_eq!(read.undo(), None);
+        read.unapply_action(&Action { column: 3 }).unwrap();
+        assert_eq!(read, "4".parse().unwrap());
+    }
+}
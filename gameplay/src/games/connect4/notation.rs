@@ -0,0 +1,248 @@
+//! Text formats for Connect4 positions.
+//!
+//! [`Moves`] are the columns played from the start of the game counting
+//! from 1, the notation other Connect4 solvers use, so `4453` is two chips
+//! in the middle column followed by one either side.
+//!
+//! Boards display as a diagram with the top row first, `x` for player 0,
+//! `o` for player 1 and `.` for empty cells:
+//!
+//! ```text
+//! .......
+//! .......
+//! .......
+//! .......
+//! ...o...
+//! ...xxo.
+//! ```
+//!
+//! Boards parse from either format. A diagram doesn't say whose turn it
+//! is, it's player 0's when both have played as many chips.
+
+use std::fmt;
+use std::str::FromStr;
+
+use super::{Action, Bitboard, Connect4, Error, COLS, ROWS};
+use crate::games::{GameState, GameStatus};
+
+/// Columns played from the start of the game.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Moves(pub Vec<Action>);
+
+impl Moves {
+    /// The position after playing the moves.
+    pub fn play<S>(&self) -> Result<S, Error>
+    where
+        S: GameState<Action = Action, Error = Error>,
+    {
+        let mut state = S::default();
+        for (i, action) in self.0.iter().enumerate() {
+            let invalid = |message: &str| {
+                Error::InvalidMoves(format!(
+                    "move {} in column {} {}",
+                    i + 1,
+                    action.column + 1,
+                    message
+                ))
+            };
+            if let GameStatus::Over { .. } = state.status() {
+                return Err(invalid("is after the game is over"));
+            }
+            if !state.valid_action(action) {
+                return Err(invalid("is in a full column"));
+            }
+            state.apply_action(action)?;
+        }
+        Ok(state)
+    }
+}
+
+impl FromStr for Moves {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.trim()
+            .chars()
+            .map(|c| match c.to_digit(10) {
+                Some(column @ 1..) if column as usize <= COLS => Ok(Action {
+                    column: column as usize - 1,
+                }),
+                _ => Err(Error::InvalidMoves(format!("{:?} isn't a column", c))),
+            })
+            .collect::<Result<_, _>>()
+            .map(Moves)
+    }
+}
+
+impl fmt::Display for Moves {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for action in &self.0 {
+            write!(f, "{}", action.column + 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Connect4 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in (0..ROWS).rev() {
+            for col in 0..COLS {
+                let cell = match self.get(col, row) {
+                    Some(0) => 'x',
+                    Some(_) => 'o',
+                    None => '.',
+                };
+                write!(f, "{}", cell)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads [`Moves`] or a diagram.
+impl FromStr for Connect4 {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.trim().chars().all(|c| c.is_ascii_digit()) {
+            return s.parse::<Moves>()?.play();
+        }
+        let rows: Vec<&str> = s
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect();
+        if rows.len() != ROWS {
+            return Err(Error::InvalidBoard(format!(
+                "expected {} rows, got {}",
+                ROWS,
+                rows.len()
+            )));
+        }
+        let mut state = Connect4::default();
+        // The diagram starts with the top row.
+        for (row, line) in rows.iter().rev().enumerate() {
+            if line.chars().count() != COLS {
+                return Err(Error::InvalidBoard(format!(
+                    "expected {} cells in row {}, got `{}`",
+                    COLS,
+                    row + 1,
+                    line
+                )));
+            }
+            for (col, c) in line.chars().enumerate() {
+                let cell = match c {
+                    'x' => Some(0),
+                    'o' => Some(1),
+                    '.' => None,
+                    _ => {
+                        return Err(Error::InvalidBoard(format!(
+                            "{:?} isn't a chip or an empty cell",
+                            c
+                        )))
+                    }
+                };
+                state.set(col, row, cell);
+            }
+        }
+        // It's the turn of whoever has played fewer chips, `validate` checks
+        // the counts are possible and that no chips are floating.
+        let chips = |player| {
+            state
+                .board
+                .iter()
+                .filter(|&&cell| cell == Some(player))
+                .count()
+        };
+        state.next_player = if chips(0) > chips(1) { 1 } else { 0 };
+        state.validate()?;
+        state.refresh();
+        Ok(state)
+    }
+}
+
+impl fmt::Display for Bitboard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Connect4::from(self.clone()).fmt(f)
+    }
+}
+
+/// Reads [`Moves`] or a diagram.
+impl FromStr for Bitboard {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse::<Connect4>()?.try_into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn moves_round_trip() {
+        let moves: Moves = "4453".parse().unwrap();
+        let columns: Vec<usize> = moves.0.iter().map(|action| action.column).collect();
+        assert_eq!(columns, vec![3, 3, 4, 2]);
+        assert_eq!(moves.to_string(), "4453");
+
+        assert!("4483".parse::<Moves>().is_err());
+        assert!("40".parse::<Moves>().is_err());
+    }
+
+    #[test]
+    fn moves_must_be_playable() {
+        assert!(matches!(
+            "4444444".parse::<Moves>().unwrap().play::<Connect4>(),
+            Err(Error::InvalidMoves(_))
+        ));
+        // Player 0 has four in column 1 after the 7th move.
+        assert!("12121211"
+            .parse::<Moves>()
+            .unwrap()
+            .play::<Bitboard>()
+            .is_err());
+    }
+
+    #[test]
+    fn diagrams_round_trip() {
+        let state: Connect4 = "4456".parse().unwrap();
+        let diagram = "\
+.......
+.......
+.......
+.......
+...o...
+...xxo.
+";
+        assert_eq!(state.to_string(), diagram);
+        assert_eq!(diagram.parse::<Connect4>().unwrap(), state);
+
+        let bitboard: Bitboard = diagram.parse().unwrap();
+        assert_eq!(bitboard.to_string(), diagram);
+    }
+
+    #[test]
+    fn diagram_turn_comes_from_the_chips() {
+        let state: Connect4 = "445".parse().unwrap();
+        assert_eq!(state.to_string().parse::<Connect4>().unwrap(), state);
+        assert_eq!(state.status(), GameStatus::InProgress { next_player: 1 });
+    }
+
+    #[test]
+    fn rejects_impossible_diagrams() {
+        let floating = ".......\n.......\n.......\n.......\n...x...\n.......\n";
+        let too_many = ".......\n.......\n.......\n.......\n.......\n..xx...\n";
+        let player_1_first = ".......\n.......\n.......\n.......\n.......\n...o...\n";
+        let short = ".......\n.......\n";
+        let unknown = ".......\n.......\n.......\n.......\n.......\n...?...\n";
+        for diagram in [floating, too_many, player_1_first, short, unknown] {
+            assert!(matches!(
+                diagram.parse::<Connect4>(),
+                Err(Error::InvalidBoard(_))
+            ));
+        }
+    }
+}
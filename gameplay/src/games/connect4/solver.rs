@@ -0,0 +1,750 @@
+//! Solves Connect4 positions, finding who wins with perfect play and how
+//! many moves it takes.
+//!
+//! This is the solver from Pascal Pons' [Connect4 solver
+//! tutorial](http://blog.gamesolver.org/): a negamax search with alpha-beta
+//! pruning over bitboards, narrowed down to the exact score with null
+//! window searches. It never plays a move that lets the opponent win right
+//! away, tries moves that make the most threats first (center columns first
+//! when they tie) and remembers bounds on positions in a transposition
+//! table. Positions near the start of the game take a long time to solve,
+//! a [`Book`] of solved opening positions skips those searches.
+//!
+//! Scores are the ones other Connect4 solvers use. A position where the
+//! player to move wins scores 22 less the number of chips they have played
+//! when they win, a loss is the negative of the opponent's score and a draw
+//! is 0.
+
+use std::cmp::{Ordering, Reverse};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::time::{Duration, Instant};
+
+use super::bitboard::{FULL, HEIGHT};
+use super::{Action, Bitboard, Connect4, Moves, COLS, ROWS};
+use crate::agents::Agent;
+use crate::games::{GameState, GameStatus};
+
+const CELLS: usize = ROWS * COLS;
+
+/// The bottom cell of every column.
+const BOTTOM: u64 = {
+    let mut bottom = 0;
+    let mut col = 0;
+    while col < COLS {
+        bottom |= 1 << (col * HEIGHT);
+        col += 1;
+    }
+    bottom
+};
+
+/// Columns from the center out, the order moves are tried in when they
+/// make as many threats.
+const COLUMN_ORDER: [usize; COLS] = [3, 2, 4, 1, 5, 0, 6];
+
+/// Entries in the transposition table by default, a prime so keys spread
+/// out. 16MB.
+const TABLE_SIZE: usize = 1_048_573;
+
+fn column_mask(col: usize) -> u64 {
+    ((1 << ROWS) - 1) << (col * HEIGHT)
+}
+
+/// The empty cells that would complete a line of four for `chips`.
+fn winning_cells(chips: u64, mask: u64) -> u64 {
+    // Vertical, only upwards.
+    let mut cells = (chips << 1) & (chips << 2) & (chips << 3);
+    // Horizontal and both diagonals, the missing chip can be anywhere.
+    for shift in [HEIGHT, HEIGHT - 1, HEIGHT + 1] {
+        let pair = (chips << shift) & (chips << (2 * shift));
+        cells |= pair & (chips << (3 * shift));
+        cells |= pair & (chips >> shift);
+        let pair = (chips >> shift) & (chips >> (2 * shift));
+        cells |= pair & (chips << shift);
+        cells |= pair & (chips >> (3 * shift));
+    }
+    cells & (FULL ^ mask)
+}
+
+/// `bits` with the columns in reverse order.
+fn mirror(bits: u64) -> u64 {
+    (0..COLS).fold(0, |mirrored, col| {
+        let column = (bits >> (col * HEIGHT)) & ((1 << HEIGHT) - 1);
+        mirrored | column << ((COLS - 1 - col) * HEIGHT)
+    })
+}
+
+/// A position seen from the side of the player to move.
+#[derive(Clone, Copy)]
+struct Position {
+    /// Chips of the player to move.
+    current: u64,
+    /// Every chip.
+    mask: u64,
+    moves: usize,
+}
+
+impl From<&Bitboard> for Position {
+    fn from(state: &Bitboard) -> Self {
+        let mask = state.mask();
+        let next_player = match state.status() {
+            GameStatus::InProgress { next_player } => next_player,
+            GameStatus::Over { .. } => panic!("Only games in progress can be solved"),
+        };
+        Self {
+            current: state.chips(next_player),
+            mask,
+            moves: mask.count_ones() as usize,
+        }
+    }
+}
+
+impl Position {
+    /// Unique for every position, `current` plus `mask` sets the bit above
+    /// the top chip of each column and keeps the current player's chips.
+    fn key(&self) -> u64 {
+        self.current + self.mask
+    }
+
+    /// The same for a position and its mirror image, which have the same
+    /// score.
+    fn symmetric_key(&self) -> u64 {
+        self.key().min(mirror(self.key()))
+    }
+
+    /// The cell each column's next chip would go in.
+    fn possible(&self) -> u64 {
+        (self.mask + BOTTOM) & FULL
+    }
+
+    /// Play the chip at `cell`, one of `possible()`.
+    fn play(&mut self, cell: u64) {
+        self.current ^= self.mask;
+        self.mask |= cell;
+        self.moves += 1;
+    }
+
+    fn can_win_next(&self) -> bool {
+        winning_cells(self.current, self.mask) & self.possible() != 0
+    }
+
+    /// Moves that don't let the opponent win on their next move. Assumes
+    /// the player to move can't win right away.
+    fn non_losing_moves(&self) -> u64 {
+        let mut possible = self.possible();
+        let threats = winning_cells(self.current ^ self.mask, self.mask);
+        let forced = possible & threats;
+        if forced != 0 {
+            if forced & (forced - 1) != 0 {
+                // Two threats, one can't block both.
+                return 0;
+            }
+            possible = forced;
+        }
+        // Don't play below a cell the opponent wins with.
+        possible & !(threats >> 1)
+    }
+
+    /// How many lines of four a move would be one chip away from.
+    fn threats_after(&self, cell: u64) -> u32 {
+        winning_cells(self.current | cell, self.mask).count_ones()
+    }
+
+    /// Score of the player to move winning with their next chip.
+    fn win_score(&self) -> i32 {
+        (CELLS as i32 + 1 - self.moves as i32) / 2
+    }
+}
+
+/// Who wins a position with perfect play.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Outcome {
+    Loss,
+    Draw,
+    Win,
+}
+
+/// The value of a position with perfect play, for the player to move.
+/// Better values compare greater, a win is better the sooner it is and a
+/// loss the later.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Value {
+    pub outcome: Outcome,
+    /// Moves, counting both players, until the game is over.
+    pub moves: usize,
+}
+
+impl Value {
+    /// The value of `score` in a position where `played` chips have been
+    /// played.
+    fn from_score(score: i32, played: usize) -> Self {
+        // Chips the player to move and their opponent have played.
+        let mine = played as i32 / 2;
+        let theirs = played as i32 - mine;
+        match score.cmp(&0) {
+            Ordering::Greater => Value {
+                outcome: Outcome::Win,
+                moves: (2 * (22 - score - mine) - 1) as usize,
+            },
+            Ordering::Less => Value {
+                outcome: Outcome::Loss,
+                moves: (2 * (22 + score - theirs)) as usize,
+            },
+            Ordering::Equal => Value {
+                outcome: Outcome::Draw,
+                moves: CELLS - played,
+            },
+        }
+    }
+}
+
+impl Ord for Value {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let moves = match self.outcome {
+            Outcome::Loss => self.moves.cmp(&other.moves),
+            Outcome::Draw | Outcome::Win => other.moves.cmp(&self.moves),
+        };
+        self.outcome.cmp(&other.outcome).then(moves)
+    }
+}
+
+impl PartialOrd for Value {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.outcome {
+            Outcome::Win => write!(f, "win in {}", self.moves),
+            Outcome::Draw => write!(f, "draw"),
+            Outcome::Loss => write!(f, "loss in {}", self.moves),
+        }
+    }
+}
+
+/// Scores of solved positions, usually the openings.
+///
+/// Books are text files with a position on each line, the columns played
+/// to get there counting from 1 and its score, like `4453 -2`.
+#[derive(Debug, Default, Clone)]
+pub struct Book {
+    scores: HashMap<u64, i8>,
+}
+
+impl Book {
+    pub fn read(reader: impl BufRead) -> io::Result<Self> {
+        let invalid = |line: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid book line `{}`", line),
+            )
+        };
+        let mut book = Book::default();
+        for line in reader.lines() {
+            let line = line?;
+            let Some((moves, score)) = line.split_once(' ') else {
+                return Err(invalid(&line));
+            };
+            let state: Bitboard = moves
+                .parse::<Moves>()
+                .and_then(|moves| moves.play())
+                .map_err(|_| invalid(&line))?;
+            if let GameStatus::Over { .. } = state.status() {
+                return Err(invalid(&line));
+            }
+            let score = score.trim().parse().map_err(|_| invalid(&line))?;
+            book.scores
+                .insert(Position::from(&state).symmetric_key(), score);
+        }
+        Ok(book)
+    }
+
+    pub fn len(&self) -> usize {
+        self.scores.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scores.is_empty()
+    }
+
+    fn get(&self, position: &Position) -> Option<i32> {
+        self.scores
+            .get(&position.symmetric_key())
+            .map(|&score| score as i32)
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Bound {
+    Lower,
+    Upper,
+}
+
+#[derive(Clone, Copy)]
+struct Entry {
+    key: u64,
+    score: i8,
+    bound: Bound,
+}
+
+impl Entry {
+    /// No position has this key.
+    const EMPTY: Entry = Entry {
+        key: u64::MAX,
+        score: 0,
+        bound: Bound::Upper,
+    };
+}
+
+/// Ran out of time, the position wasn't solved.
+struct OutOfTime;
+
+pub struct Solver {
+    table: Vec<Entry>,
+    book: Book,
+    deadline: Option<Instant>,
+    nodes: u64,
+}
+
+impl Default for Solver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Solver {
+    pub fn new() -> Self {
+        Self {
+            table: vec![Entry::EMPTY; TABLE_SIZE],
+            book: Book::default(),
+            deadline: None,
+            nodes: 0,
+        }
+    }
+
+    /// Look up positions in `book` instead of searching them.
+    pub fn with_book(mut self, book: Book) -> Self {
+        self.book = book;
+        self
+    }
+
+    /// Remember up to `entries` positions in the transposition table
+    /// instead of the default million. Entries are 16 bytes, a bigger table
+    /// solves positions near the start of the game faster.
+    pub fn with_table_size(mut self, entries: usize) -> Self {
+        self.table = vec![Entry::EMPTY; entries.max(1)];
+        self
+    }
+
+    /// Positions searched since the solver was made.
+    pub fn nodes(&self) -> u64 {
+        self.nodes
+    }
+
+    /// The value of `state` for the player to move. Panics if the game is
+    /// over.
+    pub fn solve(&mut self, state: &Bitboard) -> Value {
+        self.deadline = None;
+        let Ok(value) = self.solve_position(state) else {
+            unreachable!("there's no time limit")
+        };
+        value
+    }
+
+    /// Like [`Solver::solve`] but gives up after `time`.
+    pub fn solve_within(&mut self, state: &Bitboard, time: Duration) -> Option<Value> {
+        self.deadline = Some(Instant::now() + time);
+        self.solve_position(state).ok()
+    }
+
+    /// The value of every legal action in `state`, for the player to move.
+    pub fn analyze(&mut self, state: &Bitboard) -> Vec<(Action, Value)> {
+        self.deadline = None;
+        let Ok(values) = self.analyze_actions(state) else {
+            unreachable!("there's no time limit")
+        };
+        values
+    }
+
+    /// Like [`Solver::analyze`] but gives up after `time`. Positions near the
+    /// start of the game can take minutes to solve. What was learned before
+    /// giving up is kept, so trying again later is faster.
+    pub fn analyze_within(
+        &mut self,
+        state: &Bitboard,
+        time: Duration,
+    ) -> Option<Vec<(Action, Value)>> {
+        self.deadline = Some(Instant::now() + time);
+        self.analyze_actions(state).ok()
+    }
+
+    /// The best action in `state`.
+    pub fn best_action(&mut self, state: &Bitboard) -> Action {
+        best(&self.analyze(state))
+    }
+
+    /// Solve every position up to `depth` moves on from `from` and write
+    /// them to `out` as a book. Mirror images are only written once. Returns
+    /// how many positions were written.
+    pub fn write_book(
+        &mut self,
+        from: &Moves,
+        depth: usize,
+        out: &mut impl Write,
+    ) -> io::Result<usize> {
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidInput, message);
+        let start: Bitboard = from.play().map_err(|err| invalid(err.to_string()))?;
+        if let GameStatus::Over { .. } = start.status() {
+            return Err(invalid(format!("the game is over after {}", from)));
+        }
+
+        self.deadline = None;
+        let mut seen = HashSet::new();
+        let mut stack = vec![(start, from.clone())];
+        while let Some((state, moves)) = stack.pop() {
+            let position = Position::from(&state);
+            if !seen.insert(position.symmetric_key()) {
+                continue;
+            }
+            let Ok(score) = self.score(&position) else {
+                unreachable!("there's no time limit")
+            };
+            writeln!(out, "{} {}", moves, score)?;
+            if moves.0.len() == from.0.len() + depth {
+                continue;
+            }
+            for action in state.legal_actions() {
+                let mut next = state.clone();
+                if let GameStatus::InProgress { .. } = next.apply_action(&action).unwrap() {
+                    let mut moves = moves.clone();
+                    moves.0.push(action);
+                    stack.push((next, moves));
+                }
+            }
+        }
+        Ok(seen.len())
+    }
+
+    fn solve_position(&mut self, state: &Bitboard) -> Result<Value, OutOfTime> {
+        let position = Position::from(state);
+        Ok(Value::from_score(self.score(&position)?, position.moves))
+    }
+
+    fn analyze_actions(&mut self, state: &Bitboard) -> Result<Vec<(Action, Value)>, OutOfTime> {
+        let mut values = vec![];
+        for action in state.legal_actions() {
+            let mut next = state.clone();
+            let value = match next.apply_action(&action).unwrap() {
+                GameStatus::Over { result } if result.winner().is_some() => Value {
+                    outcome: Outcome::Win,
+                    moves: 1,
+                },
+                GameStatus::Over { .. } => Value {
+                    outcome: Outcome::Draw,
+                    moves: 1,
+                },
+                GameStatus::InProgress { .. } => {
+                    let position = Position::from(&next);
+                    Value::from_score(-self.score(&position)?, position.moves - 1)
+                }
+            };
+            values.push((action, value));
+        }
+        Ok(values)
+    }
+
+    /// The exact score of `position`, found by narrowing the possible
+    /// scores with null window searches.
+    fn score(&mut self, position: &Position) -> Result<i32, OutOfTime> {
+        if position.can_win_next() {
+            return Ok(position.win_score());
+        }
+        let mut min = -(CELLS as i32 - position.moves as i32) / 2;
+        let mut max = position.win_score();
+        while min < max {
+            let mut middle = min + (max - min) / 2;
+            // Search near 0 first, most positions are close to a draw.
+            if middle <= 0 && min / 2 < middle {
+                middle = min / 2;
+            } else if middle >= 0 && max / 2 > middle {
+                middle = max / 2;
+            }
+            let score = self.negamax(position, middle, middle + 1)?;
+            if score <= middle {
+                max = score;
+            } else {
+                min = score;
+            }
+        }
+        Ok(min)
+    }
+
+    /// The score of `position` if it's between `alpha` and `beta`,
+    /// otherwise a bound on it that's outside them. The player to move
+    /// can't win right away.
+    fn negamax(
+        &mut self,
+        position: &Position,
+        mut alpha: i32,
+        mut beta: i32,
+    ) -> Result<i32, OutOfTime> {
+        self.nodes += 1;
+        if self.nodes & 0xffff == 0 && self.deadline.is_some_and(|d| Instant::now() >= d) {
+            return Err(OutOfTime);
+        }
+
+        let moves = position.non_losing_moves();
+        if moves == 0 {
+            // Whatever we play the opponent wins with their next chip.
+            return Ok(-(CELLS as i32 - position.moves as i32) / 2);
+        }
+        if position.moves >= CELLS - 2 {
+            // Nobody can win with the last two chips.
+            return Ok(0);
+        }
+        if let Some(score) = self.book.get(position) {
+            return Ok(score);
+        }
+
+        // We can't win right away and neither can the opponent after our move.
+        let min = -(CELLS as i32 - 2 - position.moves as i32) / 2;
+        if alpha < min {
+            alpha = min;
+            if alpha >= beta {
+                return Ok(alpha);
+            }
+        }
+        let mut max = (CELLS as i32 - 1 - position.moves as i32) / 2;
+
+        let key = position.key();
+        let slot = (key % self.table.len() as u64) as usize;
+        let entry = self.table[slot];
+        if entry.key == key {
+            let score = entry.score as i32;
+            match entry.bound {
+                Bound::Lower if alpha < score => {
+                    alpha = score;
+                    if alpha >= beta {
+                        return Ok(alpha);
+                    }
+                }
+                Bound::Upper => max = max.min(score),
+                Bound::Lower => {}
+            }
+        }
+        if beta > max {
+            beta = max;
+            if alpha >= beta {
+                return Ok(beta);
+            }
+        }
+
+        // Moves that make the most threats first. Sorting is stable so the
+        // center columns stay first among equals.
+        let mut ordered = [(0, 0); COLS];
+        let mut count = 0;
+        for col in COLUMN_ORDER {
+            let cell = moves & column_mask(col);
+            if cell != 0 {
+                ordered[count] = (cell, position.threats_after(cell));
+                count += 1;
+            }
+        }
+        let ordered = &mut ordered[..count];
+        ordered.sort_by_key(|&(_, threats)| Reverse(threats));
+
+        for &(cell, _) in ordered.iter() {
+            let mut next = *position;
+            next.play(cell);
+            let score = -self.negamax(&next, -beta, -alpha)?;
+            if score >= beta {
+                self.table[slot] = Entry {
+                    key,
+                    score: score as i8,
+                    bound: Bound::Lower,
+                };
+                return Ok(score);
+            }
+            alpha = alpha.max(score);
+        }
+        self.table[slot] = Entry {
+            key,
+            score: alpha as i8,
+            bound: Bound::Upper,
+        };
+        Ok(alpha)
+    }
+}
+
+/// The best of the analyzed `values`, towards the center if there's a tie.
+pub fn best(values: &[(Action, Value)]) -> Action {
+    let rank = |column: usize| COLUMN_ORDER.iter().position(|&c| c == column);
+    values
+        .iter()
+        .max_by(|(a, a_value), (b, b_value)| {
+            a_value
+                .cmp(b_value)
+                .then(rank(b.column).cmp(&rank(a.column)))
+        })
+        .unwrap()
+        .0
+}
+
+impl Agent<Bitboard> for Solver {
+    fn choose(&mut self, state: &Bitboard) -> Action {
+        self.best_action(state)
+    }
+}
+
+impl Agent<Connect4> for Solver {
+    fn choose(&mut self, state: &Connect4) -> Action {
+        let state = Bitboard::try_from(state.clone()).expect("Connect4 states are valid boards");
+        self.best_action(&state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::seq::SliceRandom;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    fn position(moves: &str) -> Bitboard {
+        moves.parse().unwrap()
+    }
+
+    /// The score of `state` found by trying every move.
+    fn brute_force(state: &Bitboard) -> i32 {
+        let played = state.mask().count_ones() as i32;
+        state
+            .legal_actions()
+            .iter()
+            .map(|action| {
+                let mut next = state.clone();
+                match next.apply_action(action).unwrap() {
+                    GameStatus::Over { result } if result.winner().is_some() => {
+                        (CELLS as i32 + 1 - played) / 2
+                    }
+                    GameStatus::Over { .. } => 0,
+                    GameStatus::InProgress { .. } => -brute_force(&next),
+                }
+            })
+            .max()
+            .unwrap()
+    }
+
+    #[test]
+    fn agrees_with_brute_force() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut solver = Solver::new();
+        let mut solved = 0;
+        while solved < 20 {
+            // A random game stopped 8 moves from a full board.
+            let mut state = Bitboard::default();
+            while state.mask().count_ones() < 34 {
+                match state.legal_actions().choose(&mut rng) {
+                    Some(action) => state.apply_action(action).unwrap(),
+                    None => break,
+                };
+            }
+            if let GameStatus::InProgress { .. } = state.status() {
+                let position = Position::from(&state);
+                assert_eq!(solver.score(&position).ok(), Some(brute_force(&state)));
+                solved += 1;
+            }
+        }
+    }
+
+    #[test]
+    fn values_count_moves_to_the_end() {
+        let mut solver = Solver::new();
+        // Blue can win with its next chip.
+        let value = solver.solve(&position("121212"));
+        assert_eq!(
+            value,
+            Value {
+                outcome: Outcome::Win,
+                moves: 1
+            }
+        );
+        // Blue plays 3 to threaten both 0 and 4, red can only block one.
+        let value = solver.solve(&position("2233"));
+        assert_eq!(
+            value,
+            Value {
+                outcome: Outcome::Win,
+                moves: 3
+            }
+        );
+    }
+
+    #[test]
+    fn analyze_finds_the_only_good_move() {
+        let mut solver = Solver::new();
+        // Blue has to block column 2, red wins there after any other move.
+        let state = position("757242511771517123125537625466");
+        let values = solver.analyze(&state);
+        assert_eq!(values.len(), 4);
+        for (action, value) in values {
+            if action.column == 2 {
+                assert_ne!(value.outcome, Outcome::Loss);
+            } else {
+                assert_eq!(
+                    value,
+                    Value {
+                        outcome: Outcome::Loss,
+                        moves: 2
+                    }
+                );
+            }
+        }
+        assert_eq!(solver.best_action(&state).column, 2);
+    }
+
+    #[test]
+    fn gives_up_when_out_of_time() {
+        let mut solver = Solver::new();
+        let values = solver.analyze_within(&Bitboard::default(), Duration::from_millis(10));
+        assert!(values.is_none());
+    }
+
+    #[test]
+    fn reads_a_book() {
+        // Not the real score, so it can only have come from the book.
+        let book = "757242511771517123125537625466 5\n";
+        let book = Book::read(book.as_bytes()).unwrap();
+        assert_eq!(book.len(), 1);
+        let mut solver = Solver::new().with_book(book);
+        let state = position("757242511771517123125537625466");
+        assert_eq!(solver.score(&Position::from(&state)).ok(), Some(5));
+
+        assert!(Book::read("48 x".as_bytes()).is_err());
+        assert!(Book::read("4448 0".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn written_books_read_back() {
+        let from: Moves = "757242511771517123125537625466".parse().unwrap();
+        let mut out = vec![];
+        let written = Solver::new().write_book(&from, 2, &mut out).unwrap();
+        let book = Book::read(out.as_slice()).unwrap();
+        assert_eq!(book.len(), written);
+
+        let mut solver = Solver::new();
+        for line in String::from_utf8(out).unwrap().lines() {
+            let (moves, score) = line.split_once(' ').unwrap();
+            let position = Position::from(&position(moves));
+            let score = score.parse().unwrap();
+            assert_eq!(book.get(&position), Some(score));
+            assert_eq!(solver.score(&position).ok(), Some(score));
+        }
+
+        let over: Moves = "1212121".parse().unwrap();
+        assert!(Solver::new().write_book(&over, 2, &mut vec![]).is_err());
+    }
+}
@@ -1,8 +1,12 @@
 pub mod connect4;
 
 use std::error::Error;
+use std::fmt;
+use std::hash::Hash;
 use std::str::FromStr;
 
+use rand::seq::SliceRandom;
+use rand::Rng;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -21,25 +25,129 @@ impl FromStr for Game {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+impl fmt::Display for Game {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Game::Connect4 => write!(f, "connect4"),
+        }
+    }
+}
+
+/// Ways a player can fail to take its turn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Fault {
+    /// The player couldn't be reached or the request failed.
+    Transport,
+    /// The response wasn't an action.
+    MalformedResponse,
+    /// The action isn't valid in the current state.
+    IllegalAction,
+    /// The player ran out of time.
+    Timeout,
+    /// The in-process agent panicked.
+    Crash,
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let fault = match self {
+            Fault::Transport => "transport error",
+            Fault::MalformedResponse => "malformed response",
+            Fault::IllegalAction => "illegal action",
+            Fault::Timeout => "timeout",
+            Fault::Crash => "crash",
+        };
+        write!(f, "{}", fault)
+    }
+}
+
+/// The cells that won a game, a straight line on the board from `from` to
+/// `to` with both ends included. Cells are `(column, row)` the way the game
+/// numbers them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Line {
+    pub from: (usize, usize),
+    pub to: (usize, usize),
+}
+
+impl Line {
+    /// Every cell on the line, starting at `from`.
+    pub fn cells(&self) -> impl Iterator<Item = (usize, usize)> {
+        let (from, to) = (self.from, self.to);
+        let step = |a: usize, b: usize| (b as isize - a as isize).signum();
+        let (dx, dy) = (step(from.0, to.0), step(from.1, to.1));
+        let len = from.0.abs_diff(to.0).max(from.1.abs_diff(to.1)) + 1;
+        (0..len as isize).map(move |i| {
+            (
+                (from.0 as isize + i * dx) as usize,
+                (from.1 as isize + i * dy) as usize,
+            )
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum GameResult {
-    Winner { winning_player: usize }, // Index of the winning player
+    Winner {
+        // Index of the winning player
+        winning_player: usize,
+        /// The line that won, for games that are won by making one.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        line: Option<Line>,
+    },
     Tie,
-    Error,
+    /// A player faulted, the match ends as a loss for them.
+    Forfeit {
+        faulted_player: usize,
+        fault: Fault,
+        winning_player: usize,
+    },
+}
+
+impl GameResult {
+    /// Index of the player credited with the win, if there is one.
+    pub fn winner(&self) -> Option<usize> {
+        match self {
+            GameResult::Winner { winning_player, .. }
+            | GameResult::Forfeit { winning_player, .. } => Some(*winning_player),
+            GameResult::Tie => None,
+        }
+    }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum GameStatus {
     InProgress { next_player: usize }, // Index of the player who's turn it is
     Over { result: GameResult },
 }
 
-pub trait GameState: Default + Clone + Serialize + for<'a> Deserialize<'a> {
+// Send and Sync so states can be shared with async players. PartialEq and
+// Hash so searches can find states and actions again.
+pub trait GameState:
+    Default + Clone + PartialEq + Hash + Send + Sync + Serialize + for<'a> Deserialize<'a>
+{
     type Error: Error;
-    type Action: Serialize + for<'a> Deserialize<'a>;
+    type Action: Clone + PartialEq + Send + Sync + Serialize + for<'a> Deserialize<'a>;
+
+    /// The game this is the state of.
+    const GAME: Game;
 
     fn valid_action(&self, action: &Self::Action) -> bool;
+    /// Every valid action. Empty when the game is over.
+    fn legal_actions(&self) -> Vec<Self::Action>;
+    /// How many valid actions there are. Zero when the game is over.
+    fn legal_action_count(&self) -> usize {
+        self.legal_actions().len()
+    }
+    /// A valid action picked uniformly at random. None when the game is over.
+    fn random_action<R: Rng + ?Sized>(&self, rng: &mut R) -> Option<Self::Action> {
+        self.legal_actions().choose(rng).cloned()
+    }
     fn status(&self) -> GameStatus;
     /// Apply an action. Mutates the game state and returns it's status.
     fn apply_action(&mut self, action: &Self::Action) -> Result<GameStatus, Self::Error>;
+    /// Undo `action`, which must be the last action applied. Searches can
+    /// apply and undo actions on one state instead of cloning it for every
+    /// position.
+    fn unapply_action(&mut self, action: &Self::Action) -> Result<(), Self::Error>;
 }
@@ -1 +1,8 @@
+pub mod agents;
 pub mod games;
+pub mod ratings;
+pub mod record;
+#[cfg(feature = "runner")]
+pub mod runner;
+pub mod search;
+pub mod sprt;
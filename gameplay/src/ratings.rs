@@ -0,0 +1,357 @@
+//! Ratings computed from match results.
+//!
+//! Players are identified by index, `0..players`. A forfeit counts as a loss
+//! for the player that faulted.
+
+use std::f64::consts::{LN_10, PI};
+
+use crate::games::GameResult;
+
+/// Ratings are on the usual chess scale, new players start at 1500.
+pub const INITIAL_RATING: f64 = 1500.0;
+
+/// One match, `players[i]` played as player `i`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Outcome {
+    pub players: [usize; 2],
+    pub result: GameResult,
+}
+
+impl Outcome {
+    /// The score for `players[0]`, 1 for a win, ½ for a tie and 0 for a loss.
+    fn score(&self) -> f64 {
+        match self.result.winner() {
+            Some(0) => 1.0,
+            Some(_) => 0.0,
+            None => 0.5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rating {
+    pub rating: f64,
+    /// Standard deviation of the rating estimate.
+    pub deviation: f64,
+}
+
+impl Rating {
+    /// 95% confidence interval.
+    pub fn interval(&self) -> (f64, f64) {
+        (
+            self.rating - 1.96 * self.deviation,
+            self.rating + 1.96 * self.deviation,
+        )
+    }
+}
+
+/// Expected score of a player rated `rating` against one rated `opponent`.
+pub fn expected_score(rating: f64, opponent: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf((opponent - rating) / 400.0))
+}
+
+/// Elo ratings fit to all the outcomes at once.
+///
+/// This is the maximum likelihood fit of the Elo model (Bradley-Terry on the
+/// Elo scale), so unlike applying K-factor updates game by game the order of
+/// the outcomes doesn't matter. Every player also gets one virtual tie
+/// against a 1500 rated player, which keeps players who won or lost every
+/// game at a finite rating. The deviation comes from the curvature of the
+/// likelihood at the fit.
+pub fn elo(players: usize, outcomes: &[Outcome]) -> Vec<Rating> {
+    // d/dr of the expected score is ln(10)/400 * e * (1 - e).
+    const SCALE: f64 = LN_10 / 400.0;
+
+    let games: Vec<(usize, usize, f64)> = outcomes
+        .iter()
+        .map(|o| (o.players[0], o.players[1], o.score()))
+        .collect();
+
+    // Gradient and (negated) second derivative of the log likelihood with
+    // respect to player i's rating, starting from the virtual tie.
+    let derivatives = |i: usize, ratings: &[f64]| {
+        let e = expected_score(ratings[i], INITIAL_RATING);
+        let mut gradient = SCALE * (0.5 - e);
+        let mut curvature = SCALE * SCALE * e * (1.0 - e);
+        for &(a, b, score) in &games {
+            let (opponent, score) = if a == i {
+                (b, score)
+            } else if b == i {
+                (a, 1.0 - score)
+            } else {
+                continue;
+            };
+            let e = expected_score(ratings[i], ratings[opponent]);
+            gradient += SCALE * (score - e);
+            curvature += SCALE * SCALE * e * (1.0 - e);
+        }
+        (gradient, curvature)
+    };
+
+    // Newton steps one player at a time, so every step sees the others'
+    // latest ratings. Updating everyone at once overshoots.
+    let mut ratings = vec![INITIAL_RATING; players];
+    for _ in 0..1000 {
+        let mut largest_step: f64 = 0.0;
+        for i in 0..players {
+            let (gradient, curvature) = derivatives(i, &ratings);
+            // Limited so one step can't overshoot wildly.
+            let step = (gradient / curvature).clamp(-200.0, 200.0);
+            ratings[i] += step;
+            largest_step = largest_step.max(step.abs());
+        }
+        if largest_step < 1e-6 {
+            break;
+        }
+    }
+
+    (0..players)
+        .map(|i| Rating {
+            rating: ratings[i],
+            deviation: 1.0 / derivatives(i, &ratings).1.sqrt(),
+        })
+        .collect()
+}
+
+/// Glicko-2 system constant, how much volatility can change.
+const TAU: f64 = 0.5;
+/// Glicko-2 works on a different scale internally.
+const GLICKO2_SCALE: f64 = 173.7178;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Glicko2Rating {
+    pub rating: Rating,
+    pub volatility: f64,
+}
+
+impl Default for Glicko2Rating {
+    fn default() -> Self {
+        Self {
+            rating: Rating {
+                rating: INITIAL_RATING,
+                deviation: 350.0,
+            },
+            volatility: 0.06,
+        }
+    }
+}
+
+/// Glicko-2 ratings, updated outcome by outcome.
+///
+/// Each outcome is its own rating period for the two players in it, so
+/// later outcomes are judged against the opponent's rating at the time.
+pub fn glicko2(players: usize, outcomes: &[Outcome]) -> Vec<Glicko2Rating> {
+    let mut ratings = vec![Glicko2Rating::default(); players];
+    for outcome in outcomes {
+        let score = outcome.score();
+        let [a, b] = outcome.players;
+        let new_a = glicko2_update(ratings[a], &[(ratings[b], score)]);
+        let new_b = glicko2_update(ratings[b], &[(ratings[a], 1.0 - score)]);
+        ratings[a] = new_a;
+        ratings[b] = new_b;
+    }
+    ratings
+}
+
+/// One Glicko-2 rating period for `player`, who played `games` against the
+/// given opponents with the given scores.
+/// See <http://www.glicko.net/glicko/glicko2.pdf> for the steps.
+fn glicko2_update(player: Glicko2Rating, games: &[(Glicko2Rating, f64)]) -> Glicko2Rating {
+    let mu = (player.rating.rating - INITIAL_RATING) / GLICKO2_SCALE;
+    let phi = player.rating.deviation / GLICKO2_SCALE;
+    let sigma = player.volatility;
+
+    if games.is_empty() {
+        let phi = (phi * phi + sigma * sigma).sqrt();
+        return Glicko2Rating {
+            rating: Rating {
+                rating: player.rating.rating,
+                deviation: phi * GLICKO2_SCALE,
+            },
+            volatility: sigma,
+        };
+    }
+
+    // Step 3 and 4, estimated variance and improvement.
+    let mut v_inv = 0.0;
+    let mut improvement = 0.0;
+    for (opponent, score) in games {
+        let mu_j = (opponent.rating.rating - INITIAL_RATING) / GLICKO2_SCALE;
+        let phi_j = opponent.rating.deviation / GLICKO2_SCALE;
+        let g = 1.0 / (1.0 + 3.0 * phi_j * phi_j / (PI * PI)).sqrt();
+        let e = 1.0 / (1.0 + (-g * (mu - mu_j)).exp());
+        v_inv += g * g * e * (1.0 - e);
+        improvement += g * (score - e);
+    }
+    let v = 1.0 / v_inv;
+    let delta = v * improvement;
+
+    // Step 5, new volatility by the Illinois algorithm.
+    let a = (sigma * sigma).ln();
+    let f = |x: f64| {
+        let ex = x.exp();
+        let d = phi * phi + v + ex;
+        ex * (delta * delta - phi * phi - v - ex) / (2.0 * d * d) - (x - a) / (TAU * TAU)
+    };
+    let mut big_a = a;
+    let mut big_b = if delta * delta > phi * phi + v {
+        (delta * delta - phi * phi - v).ln()
+    } else {
+        let mut k = 1.0;
+        while f(a - k * TAU) < 0.0 {
+            k += 1.0;
+        }
+        a - k * TAU
+    };
+    let mut f_a = f(big_a);
+    let mut f_b = f(big_b);
+    while (big_b - big_a).abs() > 1e-6 {
+        let big_c = big_a + (big_a - big_b) * f_a / (f_b - f_a);
+        let f_c = f(big_c);
+        if f_c * f_b <= 0.0 {
+            big_a = big_b;
+            f_a = f_b;
+        } else {
+            f_a /= 2.0;
+        }
+        big_b = big_c;
+        f_b = f_c;
+    }
+    let sigma = (big_a / 2.0).exp();
+
+    // Step 6 to 8, new deviation and rating.
+    let phi_star = (phi * phi + sigma * sigma).sqrt();
+    let phi = 1.0 / (1.0 / (phi_star * phi_star) + 1.0 / v).sqrt();
+    let mu = mu + phi * phi * improvement;
+
+    Glicko2Rating {
+        rating: Rating {
+            rating: mu * GLICKO2_SCALE + INITIAL_RATING,
+            deviation: phi * GLICKO2_SCALE,
+        },
+        volatility: sigma,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::Fault;
+
+    fn outcome(a: usize, b: usize, result: GameResult) -> Outcome {
+        Outcome {
+            players: [a, b],
+            result,
+        }
+    }
+
+    fn win(a: usize, b: usize) -> Outcome {
+        outcome(
+            a,
+            b,
+            GameResult::Winner {
+                winning_player: 0,
+                line: None,
+            },
+        )
+    }
+
+    #[test]
+    fn elo_orders_players() {
+        // 0 beats 1 three times out of four, 1 beats 2 three times out of four.
+        let mut outcomes = vec![];
+        for _ in 0..3 {
+            outcomes.push(win(0, 1));
+            outcomes.push(win(1, 2));
+        }
+        outcomes.push(win(1, 0));
+        outcomes.push(win(2, 1));
+
+        let ratings = elo(3, &outcomes);
+        assert!(ratings[0].rating > ratings[1].rating);
+        assert!(ratings[1].rating > ratings[2].rating);
+        // Symmetric results, so 1 sits in the middle.
+        assert!((ratings[1].rating - INITIAL_RATING).abs() < 1e-3);
+        assert!(
+            (ratings[0].rating - INITIAL_RATING + ratings[2].rating - INITIAL_RATING).abs() < 1e-3
+        );
+    }
+
+    #[test]
+    fn elo_counts_both_colors() {
+        // 0 wins 7 of 10, going first in half of them.
+        let mut outcomes = vec![];
+        for game in 0..10 {
+            let first = game % 2;
+            let winner = if game < 7 { 0 } else { 1 };
+            let winning_player = if first == winner { 0 } else { 1 };
+            outcomes.push(outcome(
+                first,
+                1 - first,
+                GameResult::Winner {
+                    winning_player,
+                    line: None,
+                },
+            ));
+        }
+        let ratings = elo(2, &outcomes);
+        assert!(ratings[0].rating > ratings[1].rating);
+    }
+
+    #[test]
+    fn forfeit_is_a_loss() {
+        let forfeit = GameResult::Forfeit {
+            faulted_player: 0,
+            fault: Fault::Timeout,
+            winning_player: 1,
+        };
+        let outcomes = vec![outcome(0, 1, forfeit); 3];
+        let ratings = elo(2, &outcomes);
+        assert!(ratings[1].rating > ratings[0].rating);
+        let ratings = glicko2(2, &outcomes);
+        assert!(ratings[1].rating.rating > ratings[0].rating.rating);
+    }
+
+    #[test]
+    fn elo_undefeated_is_finite() {
+        let outcomes = vec![win(0, 1); 10];
+        let ratings = elo(2, &outcomes);
+        assert!(ratings[0].rating.is_finite());
+        assert!(ratings[0].rating > ratings[1].rating);
+        let (low, high) = ratings[0].interval();
+        assert!(low < ratings[0].rating && ratings[0].rating < high);
+    }
+
+    #[test]
+    fn elo_deviation_shrinks_with_games() {
+        let few = elo(2, &[win(0, 1), win(1, 0)]);
+        let many = elo(2, &[win(0, 1), win(1, 0)].repeat(50));
+        assert!(many[0].deviation < few[0].deviation);
+    }
+
+    #[test]
+    fn glicko2_paper_example() {
+        let rated = |rating, deviation| Glicko2Rating {
+            rating: Rating { rating, deviation },
+            volatility: 0.06,
+        };
+        let player = rated(1500.0, 200.0);
+        let games = [
+            (rated(1400.0, 30.0), 1.0),
+            (rated(1550.0, 100.0), 0.0),
+            (rated(1700.0, 300.0), 0.0),
+        ];
+        let updated = glicko2_update(player, &games);
+        assert!((updated.rating.rating - 1464.06).abs() < 0.01);
+        assert!((updated.rating.deviation - 151.52).abs() < 0.01);
+        assert!((updated.volatility - 0.05999).abs() < 0.00001);
+    }
+
+    #[test]
+    fn glicko2_ties_keep_ratings_equal() {
+        let outcomes = vec![outcome(0, 1, GameResult::Tie); 5];
+        let ratings = glicko2(2, &outcomes);
+        assert!((ratings[0].rating.rating - ratings[1].rating.rating).abs() < 1e-9);
+        assert!(ratings[0].rating.deviation < 350.0);
+    }
+}
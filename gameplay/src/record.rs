@@ -0,0 +1,152 @@
+//! Records of played matches, so they can be archived, replayed and analyzed.
+
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::games::{Game, GameState, GameStatus};
+
+/// Milliseconds since the unix epoch.
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Who played a side of a match.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PlayerDescriptor {
+    Human,
+    Agent {
+        url: String,
+    },
+    /// An agent running in the same process.
+    Local {
+        name: String,
+    },
+    /// An agent run as a local executable.
+    Command {
+        command: String,
+    },
+}
+
+impl fmt::Display for PlayerDescriptor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlayerDescriptor::Human => write!(f, "human"),
+            PlayerDescriptor::Agent { url } => write!(f, "{}", url),
+            PlayerDescriptor::Local { name } => write!(f, "{}", name),
+            PlayerDescriptor::Command { command } => write!(f, "{}", command),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MoveRecord<A> {
+    pub player: usize,
+    pub action: A,
+    /// When the action was taken, in milliseconds since the unix epoch.
+    pub timestamp_ms: u64,
+    /// How long the player took to choose the action.
+    pub latency_ms: u64,
+}
+
+// `GameState` already requires the state and its actions to be serializable.
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct MatchRecord<S: GameState> {
+    pub game: Game,
+    pub match_id: String,
+    /// `players[i]` played as player `i`.
+    pub players: Vec<PlayerDescriptor>,
+    /// When the match started, in milliseconds since the unix epoch.
+    pub started_ms: u64,
+    pub start: S,
+    pub moves: Vec<MoveRecord<S::Action>>,
+    /// Status after the last move, or the forfeit if a player faulted. Still
+    /// `InProgress` if the match was abandoned.
+    pub status: GameStatus,
+    /// Why a player forfeited.
+    pub error: Option<String>,
+}
+
+impl<S: GameState> MatchRecord<S> {
+    pub fn new(match_id: String, players: Vec<PlayerDescriptor>, start: S) -> Self {
+        let status = start.status();
+        Self {
+            game: S::GAME,
+            match_id,
+            players,
+            started_ms: now_ms(),
+            start,
+            moves: vec![],
+            status,
+            error: None,
+        }
+    }
+
+    /// Record an action and the status of the game after it.
+    pub fn push(
+        &mut self,
+        player: usize,
+        action: S::Action,
+        latency: Duration,
+        status: GameStatus,
+    ) {
+        self.moves.push(MoveRecord {
+            player,
+            action,
+            timestamp_ms: now_ms(),
+            latency_ms: latency.as_millis() as u64,
+        });
+        self.status = status;
+    }
+
+    /// The game state after the first `moves` moves.
+    pub fn state_after(&self, moves: usize) -> Result<S, S::Error> {
+        let mut state = self.start.clone();
+        for record in self.moves.iter().take(moves) {
+            state.apply_action(&record.action)?;
+        }
+        Ok(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::games::connect4::{Action, Connect4};
+
+    #[test]
+    fn round_trip() {
+        let mut state = Connect4::default();
+        let mut record = MatchRecord::new(
+            "match".to_string(),
+            vec![
+                PlayerDescriptor::Human,
+                PlayerDescriptor::Agent {
+                    url: "http://localhost:8000/".to_string(),
+                },
+            ],
+            state.clone(),
+        );
+        for (player, column) in [3, 4, 3].into_iter().enumerate() {
+            let action = Action { column };
+            let status = state.apply_action(&action).unwrap();
+            record.push(player % 2, action, Duration::from_millis(5), status);
+        }
+
+        let json = serde_json::to_string(&record).unwrap();
+        let record: MatchRecord<Connect4> = serde_json::from_str(&json).unwrap();
+        assert_eq!(record.moves.len(), 3);
+        assert_eq!(record.moves[1].latency_ms, 5);
+        assert_eq!(record.status, state.status());
+        let replayed = record.state_after(3).unwrap();
+        assert_eq!(replayed.get(3, 1), Some(0));
+        assert_eq!(replayed.get(4, 0), Some(1));
+    }
+}
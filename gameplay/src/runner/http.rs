@@ -0,0 +1,101 @@
+//! Players that are HTTP services (see "How agents work" in the README).
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+use reqwest::{Client, RequestBuilder, Url};
+
+use super::{Player, PlayerError, Turn};
+use crate::games::{Fault, GameResult, GameState};
+use crate::record::PlayerDescriptor;
+
+/// Build a request to an agent. The body is the json game state and the
+/// headers describe the match.
+pub fn request<S: GameState>(
+    client: &Client,
+    url: &Url,
+    match_id: &str,
+    player: usize,
+    match_status: &str,
+    state: &S,
+) -> RequestBuilder {
+    client
+        .post(url.clone())
+        .header("Gameplay-Game", S::GAME.to_string())
+        .header("Gameplay-Match-ID", match_id)
+        .header("Gameplay-Player", player.to_string())
+        .header("Gameplay-Match-Status", match_status)
+        .json(state)
+}
+
+/// Tell the agent about its time with the `Gameplay-Move-Time-Ms` and
+/// `Gameplay-Remaining-Time-Ms` headers.
+fn time_headers(request: RequestBuilder, turn: &Turn<'_>) -> RequestBuilder {
+    let ms = |time: Duration| time.as_millis().to_string();
+    let mut request = request;
+    if let Some(move_time) = turn.move_time {
+        request = request.header("Gameplay-Move-Time-Ms", ms(move_time));
+    }
+    if let Some(remaining) = turn.remaining_time {
+        request = request.header("Gameplay-Remaining-Time-Ms", ms(remaining));
+    }
+    request
+}
+
+/// An agent behind an HTTP service.
+pub struct HttpAgent {
+    client: Client,
+    url: Url,
+}
+
+impl HttpAgent {
+    pub fn new(client: Client, url: Url) -> Self {
+        Self { client, url }
+    }
+}
+
+#[async_trait]
+impl<S: GameState> Player<S> for HttpAgent {
+    fn descriptor(&self) -> PlayerDescriptor {
+        PlayerDescriptor::Agent {
+            url: self.url.to_string(),
+        }
+    }
+
+    async fn choose(&mut self, turn: Turn<'_>, state: &S) -> Result<S::Action, PlayerError> {
+        let transport = |err: reqwest::Error| PlayerError::fault(Fault::Transport, err.to_string());
+        let request = request(
+            &self.client,
+            &self.url,
+            turn.match_id,
+            turn.player,
+            "InProgress",
+            state,
+        );
+        let resp = time_headers(request, &turn)
+            .send()
+            .await
+            .and_then(|resp| resp.error_for_status())
+            .map_err(transport)?;
+        let body = resp.bytes().await.map_err(transport)?;
+        serde_json::from_slice(&body)
+            .map_err(|err| PlayerError::fault(Fault::MalformedResponse, err.to_string()))
+    }
+
+    async fn match_over(&mut self, turn: Turn<'_>, state: &S, result: Option<GameResult>) {
+        let mut request = request(
+            &self.client,
+            &self.url,
+            turn.match_id,
+            turn.player,
+            "Over",
+            state,
+        );
+        if let Some(result) = result {
+            let result = serde_json::to_string(&result).unwrap();
+            request = request.header("Gameplay-Match-Result", result);
+        }
+        // The match is over either way, nothing to do if this fails.
+        let _ = request.send().await;
+    }
+}
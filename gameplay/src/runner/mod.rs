@@ -0,0 +1,480 @@
+//! Run matches between players without a terminal, so they can be embedded
+//! in services and tests. Needs the `runner` feature.
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+
+use crate::agents::Agent;
+use crate::games::{Fault, GameResult, GameState, GameStatus};
+use crate::record::{MatchRecord, PlayerDescriptor};
+
+pub mod http;
+pub mod subprocess;
+
+pub use http::HttpAgent;
+pub use subprocess::SubprocessAgent;
+
+/// How long a player gets to take the news that the match is over before
+/// the runner stops waiting for it.
+const OVER_TIME: Duration = Duration::from_secs(2);
+
+/// Time limits for players. A player that goes over forfeits the match.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TimeControl {
+    /// Time a player has for each move.
+    pub move_time: Option<Duration>,
+    /// Time a player has for all of its moves in a match.
+    pub match_time: Option<Duration>,
+}
+
+impl TimeControl {
+    /// Time left on the clock of a player that has used `used` so far.
+    pub fn remaining(&self, used: Duration) -> Option<Duration> {
+        self.match_time.map(|time| time.saturating_sub(used))
+    }
+
+    /// How long a player that has used `used` so far has for its next move.
+    pub fn limit(&self, used: Duration) -> Option<Duration> {
+        match (self.move_time, self.remaining(used)) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+}
+
+/// What a player is told about the turn it's taking.
+#[derive(Debug, Clone, Copy)]
+pub struct Turn<'a> {
+    pub match_id: &'a str,
+    /// Index of the player taking the turn.
+    pub player: usize,
+    pub move_time: Option<Duration>,
+    pub remaining_time: Option<Duration>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlayerError {
+    /// The player failed to take its turn and forfeits.
+    Fault { fault: Fault, reason: String },
+    /// The player walked away, the match ends without a result.
+    Quit,
+}
+
+impl PlayerError {
+    pub fn fault(fault: Fault, reason: impl Into<String>) -> Self {
+        PlayerError::Fault {
+            fault,
+            reason: reason.into(),
+        }
+    }
+}
+
+#[async_trait]
+pub trait Player<S: GameState>: Send {
+    /// Who this is, for the match record.
+    fn descriptor(&self) -> PlayerDescriptor;
+
+    /// Whether the time control applies to this player.
+    fn timed(&self) -> bool {
+        true
+    }
+
+    /// Choose an action for the turn. The runner checks the action is valid
+    /// and within the time limit.
+    async fn choose(&mut self, turn: Turn<'_>, state: &S) -> Result<S::Action, PlayerError>;
+
+    /// Called for both players once the match is over, with the final state
+    /// and the result. There's no result if a player quit. The runner only
+    /// waits a couple of seconds for it.
+    async fn match_over(&mut self, _turn: Turn<'_>, _state: &S, _result: Option<GameResult>) {}
+}
+
+/// A player wrapping an in-process `Agent`.
+pub struct Local<A> {
+    name: String,
+    // Shared with the blocking task that's choosing, which keeps the agent
+    // until it returns even if the turn timed out.
+    agent: Arc<Mutex<A>>,
+}
+
+impl<A> Local<A> {
+    pub fn new(name: impl Into<String>, agent: A) -> Self {
+        Self {
+            name: name.into(),
+            agent: Arc::new(Mutex::new(agent)),
+        }
+    }
+}
+
+#[async_trait]
+impl<S: GameState + 'static, A: Agent<S> + Send + 'static> Player<S> for Local<A> {
+    fn descriptor(&self) -> PlayerDescriptor {
+        PlayerDescriptor::Local {
+            name: self.name.clone(),
+        }
+    }
+
+    // Agents search for as long as they're allowed to, so they run on a
+    // blocking thread where they don't hold up the runtime and the time
+    // limit can cut them off. One that's cut off still runs until it
+    // returns, it just isn't waited for. One that panics forfeits, like an
+    // agent service that fails.
+    async fn choose(&mut self, _turn: Turn<'_>, state: &S) -> Result<S::Action, PlayerError> {
+        let agent = self.agent.clone();
+        let state = state.clone();
+        tokio::task::spawn_blocking(move || agent.lock().unwrap().choose(&state))
+            .await
+            .map_err(|err| match err.try_into_panic() {
+                Ok(panic) => {
+                    let reason = match panic.downcast::<String>() {
+                        Ok(message) => *message,
+                        Err(panic) => match panic.downcast::<&str>() {
+                            Ok(message) => message.to_string(),
+                            Err(_) => "The agent panicked".to_string(),
+                        },
+                    };
+                    PlayerError::fault(Fault::Crash, reason)
+                }
+                // The runtime is shutting down.
+                Err(_) => PlayerError::Quit,
+            })
+    }
+}
+
+/// Something that happened in a match.
+pub enum Event<'a, S: GameState> {
+    /// `player` is about to choose an action in `state`.
+    Turn { player: usize, state: &'a S },
+    /// `player` took `action` in `latency`, `state` is the state after it.
+    Action {
+        player: usize,
+        action: &'a S::Action,
+        latency: Duration,
+        state: &'a S,
+        status: GameStatus,
+    },
+    /// The match ended. `status` is still `InProgress` if a player quit.
+    Over {
+        state: &'a S,
+        status: GameStatus,
+        error: Option<&'a str>,
+    },
+}
+
+/// Play a match from `start`. `players[i]` plays as player `i`. Every
+/// event is passed to `on_event` as it happens. Returns the record of the
+/// match.
+pub async fn run_match<S: GameState>(
+    match_id: String,
+    start: S,
+    mut players: [&mut dyn Player<S>; 2],
+    time: TimeControl,
+    mut on_event: impl FnMut(Event<'_, S>) + Send,
+) -> MatchRecord<S> {
+    let mut record = MatchRecord::new(
+        match_id,
+        players.iter().map(|player| player.descriptor()).collect(),
+        start.clone(),
+    );
+    let mut state = start;
+    let mut status = state.status();
+    let times = players.each_ref().map(|player| {
+        if player.timed() {
+            time
+        } else {
+            TimeControl::default()
+        }
+    });
+    let mut used = [Duration::ZERO; 2];
+    while let GameStatus::InProgress { next_player } = status {
+        on_event(Event::Turn {
+            player: next_player,
+            state: &state,
+        });
+        let time = times[next_player];
+        let limit = time.limit(used[next_player]);
+        let turn = Turn {
+            match_id: &record.match_id,
+            player: next_player,
+            move_time: time.move_time,
+            remaining_time: time.remaining(used[next_player]),
+        };
+
+        let start = Instant::now();
+        let choice = players[next_player].choose(turn, &state);
+        let choice = match limit {
+            Some(limit) => tokio::time::timeout(limit, choice)
+                .await
+                .unwrap_or_else(|_| Err(timed_out(limit))),
+            None => choice.await,
+        };
+        let latency = start.elapsed();
+        let choice = choice.and_then(|action| match limit {
+            // Players that block can't be cut off, check them after.
+            Some(limit) if latency > limit => Err(timed_out(limit)),
+            _ if !state.valid_action(&action) => Err(PlayerError::fault(
+                Fault::IllegalAction,
+                format!(
+                    "Action is invalid: {}",
+                    serde_json::to_string(&action).unwrap_or_default()
+                ),
+            )),
+            _ => Ok(action),
+        });
+
+        match choice {
+            Ok(action) => {
+                used[next_player] += latency;
+                status = state.apply_action(&action).unwrap();
+                on_event(Event::Action {
+                    player: next_player,
+                    action: &action,
+                    latency,
+                    state: &state,
+                    status,
+                });
+                record.push(next_player, action, latency, status);
+            }
+            Err(PlayerError::Fault { fault, reason }) => {
+                record.status = GameStatus::Over {
+                    result: GameResult::Forfeit {
+                        faulted_player: next_player,
+                        fault,
+                        winning_player: 1 - next_player,
+                    },
+                };
+                record.error = Some(reason);
+                break;
+            }
+            Err(PlayerError::Quit) => break,
+        }
+    }
+
+    let result = match record.status {
+        GameStatus::Over { result } => Some(result),
+        GameStatus::InProgress { .. } => None,
+    };
+    for (player, p) in players.iter_mut().enumerate() {
+        let turn = Turn {
+            match_id: &record.match_id,
+            player,
+            move_time: times[player].move_time,
+            remaining_time: times[player].remaining(used[player]),
+        };
+        // A player that never answers can't hold up the end of the match.
+        let _ = tokio::time::timeout(OVER_TIME, p.match_over(turn, &state, result)).await;
+    }
+    on_event(Event::Over {
+        state: &state,
+        status: record.status,
+        error: record.error.as_deref(),
+    });
+    record
+}
+
+fn timed_out(limit: Duration) -> PlayerError {
+    PlayerError::fault(
+        Fault::Timeout,
+        format!("No action within {} ms", limit.as_millis()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::connect4::{Action, Connect4};
+    use crate::games::Line;
+
+    fn column(column: usize) -> impl FnMut(&Connect4) -> Action {
+        move |_: &Connect4| Action { column }
+    }
+
+    /// Never answers in time, or at all once the match is over.
+    struct Slow;
+
+    #[async_trait]
+    impl Player<Connect4> for Slow {
+        fn descriptor(&self) -> PlayerDescriptor {
+            PlayerDescriptor::Local {
+                name: "slow".to_string(),
+            }
+        }
+
+        async fn choose(
+            &mut self,
+            _turn: Turn<'_>,
+            _state: &Connect4,
+        ) -> Result<Action, PlayerError> {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            Ok(Action { column: 0 })
+        }
+
+        async fn match_over(
+            &mut self,
+            _turn: Turn<'_>,
+            _state: &Connect4,
+            _result: Option<GameResult>,
+        ) {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn plays_to_a_win() {
+        let mut a = Local::new("a", column(0));
+        let mut b = Local::new("b", column(1));
+        let mut actions = 0;
+        let mut over = None;
+        let record = run_match(
+            "match".to_string(),
+            Connect4::default(),
+            [&mut a, &mut b],
+            TimeControl::default(),
+            |event| match event {
+                Event::Action { .. } => actions += 1,
+                Event::Over { status, .. } => over = Some(status),
+                Event::Turn { .. } => {}
+            },
+        )
+        .await;
+
+        let result = GameResult::Winner {
+            winning_player: 0,
+            line: Some(Line {
+                from: (0, 0),
+                to: (0, 3),
+            }),
+        };
+        assert_eq!(record.status, GameStatus::Over { result });
+        assert_eq!(record.moves.len(), 7);
+        assert_eq!(actions, 7);
+        assert_eq!(over, Some(record.status));
+        assert_eq!(
+            record.players[1],
+            PlayerDescriptor::Local {
+                name: "b".to_string()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn illegal_action_forfeits() {
+        let mut a = Local::new("a", column(0));
+        let mut b = Local::new("b", column(7));
+        let record = run_match(
+            "match".to_string(),
+            Connect4::default(),
+            [&mut a, &mut b],
+            TimeControl::default(),
+            |_| {},
+        )
+        .await;
+
+        let result = GameResult::Forfeit {
+            faulted_player: 1,
+            fault: Fault::IllegalAction,
+            winning_player: 0,
+        };
+        assert_eq!(record.status, GameStatus::Over { result });
+        assert_eq!(record.moves.len(), 1);
+        assert!(record.error.is_some());
+    }
+
+    // Time only passes when the runtime has nothing else to do, so the
+    // minute `Slow` takes is over right away.
+    #[tokio::test(start_paused = true)]
+    async fn timeout_forfeits() {
+        let mut a = Slow;
+        let mut b = Local::new("b", column(0));
+        let time = TimeControl {
+            move_time: Some(Duration::from_millis(10)),
+            match_time: None,
+        };
+        let record = run_match(
+            "match".to_string(),
+            Connect4::default(),
+            [&mut a, &mut b],
+            time,
+            |_| {},
+        )
+        .await;
+
+        let result = GameResult::Forfeit {
+            faulted_player: 0,
+            fault: Fault::Timeout,
+            winning_player: 1,
+        };
+        assert_eq!(record.status, GameStatus::Over { result });
+    }
+
+    #[tokio::test]
+    async fn local_agents_are_cut_off() {
+        let mut a = Local::new("a", |_: &Connect4| {
+            std::thread::sleep(Duration::from_millis(500));
+            Action { column: 0 }
+        });
+        let mut b = Local::new("b", column(0));
+        let time = TimeControl {
+            move_time: Some(Duration::from_millis(10)),
+            match_time: None,
+        };
+        let start = Instant::now();
+        let record = run_match(
+            "match".to_string(),
+            Connect4::default(),
+            [&mut a, &mut b],
+            time,
+            |_| {},
+        )
+        .await;
+
+        assert!(start.elapsed() < Duration::from_millis(400));
+        let result = GameResult::Forfeit {
+            faulted_player: 0,
+            fault: Fault::Timeout,
+            winning_player: 1,
+        };
+        assert_eq!(record.status, GameStatus::Over { result });
+    }
+
+    #[tokio::test]
+    async fn panicking_agents_forfeit() {
+        let mut a = Local::new("a", |_: &Connect4| -> Action { panic!("no moves left") });
+        let mut b = Local::new("b", column(0));
+        let record = run_match(
+            "match".to_string(),
+            Connect4::default(),
+            [&mut a, &mut b],
+            TimeControl::default(),
+            |_| {},
+        )
+        .await;
+
+        let result = GameResult::Forfeit {
+            faulted_player: 0,
+            fault: Fault::Crash,
+            winning_player: 1,
+        };
+        assert_eq!(record.status, GameStatus::Over { result });
+        assert_eq!(record.error.as_deref(), Some("no moves left"));
+    }
+
+    #[test]
+    fn time_limit_is_the_tighter_clock() {
+        let time = TimeControl {
+            move_time: Some(Duration::from_millis(100)),
+            match_time: Some(Duration::from_millis(1000)),
+        };
+        assert_eq!(
+            time.limit(Duration::from_millis(500)),
+            Some(Duration::from_millis(100))
+        );
+        assert_eq!(
+            time.limit(Duration::from_millis(950)),
+            Some(Duration::from_millis(50))
+        );
+        assert_eq!(TimeControl::default().limit(Duration::ZERO), None);
+    }
+}
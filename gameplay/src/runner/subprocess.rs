@@ -0,0 +1,225 @@
+//! Players that are local executables, talking newline-delimited json over
+//! stdin and stdout.
+//!
+//! Each turn the agent gets one line with the game state and the same match
+//! metadata the HTTP headers carry, and answers with one line holding its
+//! action. When the match is over it gets a last line with `match_status`
+//! `Over` and the `result`, and its stdin is closed.
+
+use std::process::Stdio;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde::Serialize;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+
+use super::{Player, PlayerError, Turn};
+use crate::games::{Fault, GameResult, GameState};
+use crate::record::PlayerDescriptor;
+
+/// How long an agent gets to exit after the match before it is killed.
+const EXIT_TIME: Duration = Duration::from_secs(1);
+
+#[derive(Serialize)]
+struct Message<'a, S> {
+    game: String,
+    match_id: &'a str,
+    player: usize,
+    match_status: &'a str,
+    move_time_ms: Option<u128>,
+    remaining_time_ms: Option<u128>,
+    state: &'a S,
+    /// Only on the `Over` line, if the match has a result.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<GameResult>,
+}
+
+impl<'a, S: GameState> Message<'a, S> {
+    fn new(turn: &Turn<'a>, match_status: &'a str, state: &'a S) -> Self {
+        Message {
+            game: S::GAME.to_string(),
+            match_id: turn.match_id,
+            player: turn.player,
+            match_status,
+            move_time_ms: turn.move_time.map(|time| time.as_millis()),
+            remaining_time_ms: turn.remaining_time.map(|time| time.as_millis()),
+            state,
+            result: None,
+        }
+    }
+}
+
+struct Process {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: Lines<BufReader<ChildStdout>>,
+}
+
+/// An agent that runs as a child process. The process is started on its
+/// first turn and only lives for one match. Its stderr is discarded.
+pub struct SubprocessAgent {
+    program: String,
+    args: Vec<String>,
+    process: Option<Process>,
+}
+
+impl SubprocessAgent {
+    pub fn new(program: impl Into<String>, args: Vec<String>) -> Self {
+        Self {
+            program: program.into(),
+            args,
+            process: None,
+        }
+    }
+
+    fn process(&mut self) -> Result<&mut Process, PlayerError> {
+        if self.process.is_none() {
+            let mut child = Command::new(&self.program)
+                .args(&self.args)
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .kill_on_drop(true)
+                .spawn()
+                .map_err(|err| {
+                    PlayerError::fault(
+                        Fault::Transport,
+                        format!("Couldn't start {}: {}", self.program, err),
+                    )
+                })?;
+            let stdin = child.stdin.take().unwrap();
+            let stdout = BufReader::new(child.stdout.take().unwrap()).lines();
+            self.process = Some(Process {
+                child,
+                stdin,
+                stdout,
+            });
+        }
+        Ok(self.process.as_mut().unwrap())
+    }
+}
+
+async fn send<S: GameState>(
+    stdin: &mut ChildStdin,
+    message: &Message<'_, S>,
+) -> Result<(), PlayerError> {
+    let mut line = serde_json::to_vec(message).unwrap();
+    line.push(b'\n');
+    stdin
+        .write_all(&line)
+        .await
+        .map_err(|err| PlayerError::fault(Fault::Transport, err.to_string()))
+}
+
+#[async_trait]
+impl<S: GameState> Player<S> for SubprocessAgent {
+    fn descriptor(&self) -> PlayerDescriptor {
+        let mut command = self.program.clone();
+        for arg in &self.args {
+            command.push(' ');
+            command.push_str(arg);
+        }
+        PlayerDescriptor::Command { command }
+    }
+
+    async fn choose(&mut self, turn: Turn<'_>, state: &S) -> Result<S::Action, PlayerError> {
+        let process = self.process()?;
+        send(
+            &mut process.stdin,
+            &Message::new(&turn, "InProgress", state),
+        )
+        .await?;
+        let line = process
+            .stdout
+            .next_line()
+            .await
+            .map_err(|err| PlayerError::fault(Fault::Transport, err.to_string()))?
+            .ok_or_else(|| PlayerError::fault(Fault::Transport, "The agent exited"))?;
+        serde_json::from_str(&line)
+            .map_err(|err| PlayerError::fault(Fault::MalformedResponse, err.to_string()))
+    }
+
+    async fn match_over(&mut self, turn: Turn<'_>, state: &S, result: Option<GameResult>) {
+        let Some(mut process) = self.process.take() else {
+            return;
+        };
+        let message = Message {
+            result,
+            ..Message::new(&turn, "Over", state)
+        };
+        // The match is over either way, nothing to do if this fails.
+        let _ = send(&mut process.stdin, &message).await;
+        drop(process.stdin);
+        let _ = tokio::time::timeout(EXIT_TIME, process.child.wait()).await;
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::games::connect4::{Action, Connect4};
+    use crate::games::{GameResult, GameStatus, Line};
+    use crate::runner::{run_match, Local, TimeControl};
+
+    fn shell(script: &str) -> SubprocessAgent {
+        SubprocessAgent::new("sh", vec!["-c".to_string(), script.to_string()])
+    }
+
+    async fn play(agent: &mut SubprocessAgent) -> GameStatus {
+        let mut other = Local::new("other", |_: &Connect4| Action { column: 0 });
+        let record = run_match(
+            "match".to_string(),
+            Connect4::default(),
+            [agent, &mut other],
+            TimeControl::default(),
+            |_| {},
+        )
+        .await;
+        record.status
+    }
+
+    #[tokio::test]
+    async fn plays_over_stdio() {
+        let mut agent = shell(r#"while read line; do echo '{"column": 3}'; done"#);
+        let result = GameResult::Winner {
+            winning_player: 0,
+            line: Some(Line {
+                from: (3, 0),
+                to: (3, 3),
+            }),
+        };
+        assert_eq!(play(&mut agent).await, GameStatus::Over { result });
+    }
+
+    #[tokio::test]
+    async fn last_line_has_the_result() {
+        let path = std::env::temp_dir().join(format!("gameplay-over-{}", std::process::id()));
+        let script = format!(
+            r#"while read line; do echo "$line" > {}; echo '{{"column": 3}}'; done"#,
+            path.display()
+        );
+        play(&mut shell(&script)).await;
+        let last = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let last: serde_json::Value = serde_json::from_str(&last).unwrap();
+        assert_eq!(last["match_status"], "Over");
+        assert_eq!(last["result"]["Winner"]["winning_player"], 0);
+        assert_eq!(
+            last["result"]["Winner"]["line"]["from"],
+            serde_json::json!([3, 0])
+        );
+    }
+
+    #[tokio::test]
+    async fn exiting_forfeits() {
+        let mut agent = shell("exit 0");
+        let result = GameResult::Forfeit {
+            faulted_player: 0,
+            fault: Fault::Transport,
+            winning_player: 1,
+        };
+        assert_eq!(play(&mut agent).await, GameStatus::Over { result });
+    }
+}
@@ -0,0 +1,410 @@
+//! Negamax search with alpha-beta pruning for any game.
+//!
+//! [`Negamax`] scores positions with an evaluation function you give it.
+//! It searches one move deeper at a time (iterative deepening) until it
+//! reaches its depth or runs out of time, and keeps what it learns about
+//! each position in a transposition table keyed by a hash of the state.
+//! Entries also keep a second hash that's checked when they're found, so a
+//! collision of the keys isn't mistaken for the same position.
+//! The best action from the table is always tried first, after that actions
+//! are tried in the order of the ordering hook, if there is one.
+//!
+//! ```
+//! use gameplay::agents::Agent;
+//! use gameplay::games::connect4::{Connect4, ROWS};
+//! use gameplay::games::{GameState, GameStatus};
+//! use gameplay::search::Negamax;
+//!
+//! // Prefer having chips in the middle column.
+//! let mut search = Negamax::new(
+//!     |state: &Connect4| {
+//!         let GameStatus::InProgress { next_player: me } = state.status() else {
+//!             unreachable!("only games in progress are evaluated")
+//!         };
+//!         (0..ROWS)
+//!             .filter_map(|row| state.get(3, row))
+//!             .map(|player| if player == me { 1 } else { -1 })
+//!             .sum()
+//!     },
+//!     6,
+//! );
+//! let action = search.choose(&Connect4::default());
+//! assert_eq!(action.column, 3);
+//! ```
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::time::{Duration, Instant};
+
+use crate::agents::Agent;
+use crate::games::{GameState, GameStatus};
+
+/// Score of a win, less the number of moves it takes so faster wins are
+/// preferred. Evaluations should stay well below `WIN - MAX_PLY`.
+pub const WIN: i32 = 1_000_000;
+
+/// Deepest the search ever goes.
+pub const MAX_PLY: usize = 1_000;
+
+/// Values this big are forced wins (or losses) rather than evaluations.
+const FORCED: i32 = WIN - MAX_PLY as i32;
+
+/// Out of time, the search of the current depth is thrown away.
+struct OutOfTime;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Bound {
+    Exact,
+    /// The value is at least this, the search was cut off.
+    Lower,
+    /// The value is at most this, nothing beat alpha.
+    Upper,
+}
+
+struct Entry<A> {
+    /// Second hash of the state, see [`hash`].
+    check: u64,
+    depth: usize,
+    value: i32,
+    bound: Bound,
+    action: Option<A>,
+}
+
+/// Forced results are stored as moves from the stored position instead of
+/// from the root, so they're right wherever the position is found again.
+fn to_table(value: i32, ply: usize) -> i32 {
+    match value {
+        v if v > FORCED => v + ply as i32,
+        v if v < -FORCED => v - ply as i32,
+        v => v,
+    }
+}
+
+fn from_table(value: i32, ply: usize) -> i32 {
+    match value {
+        v if v > FORCED => v - ply as i32,
+        v if v < -FORCED => v + ply as i32,
+        v => v,
+    }
+}
+
+/// The result of a search.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Searched<A> {
+    pub action: A,
+    /// Value of the action for the player to move. Above `WIN - MAX_PLY` is
+    /// a forced win in `WIN - value` moves, below `-(WIN - MAX_PLY)` a
+    /// forced loss.
+    pub value: i32,
+    /// The deepest search that finished.
+    pub depth: usize,
+    /// Positions visited in this search.
+    pub nodes: u64,
+}
+
+type Order<S> = Box<dyn FnMut(&S, &mut [<S as GameState>::Action]) + Send>;
+
+pub struct Negamax<S: GameState, E> {
+    evaluate: E,
+    depth: usize,
+    time: Option<Duration>,
+    order: Option<Order<S>>,
+    table: HashMap<u64, Entry<S::Action>>,
+    deadline: Option<Instant>,
+    nodes: u64,
+}
+
+impl<S, E> Negamax<S, E>
+where
+    S: GameState,
+    E: FnMut(&S) -> i32,
+{
+    /// Search up to `depth` moves ahead. `evaluate` scores positions where
+    /// the game isn't over from the side of the player to move.
+    pub fn new(evaluate: E, depth: usize) -> Self {
+        Self {
+            evaluate,
+            depth: depth.clamp(1, MAX_PLY),
+            time: None,
+            order: None,
+            table: HashMap::new(),
+            deadline: None,
+            nodes: 0,
+        }
+    }
+
+    /// Stop deepening when `time` is up and play the best action of the
+    /// deepest search that finished.
+    pub fn time_limit(mut self, time: Duration) -> Self {
+        self.time = Some(time);
+        self
+    }
+
+    /// Sort the actions of a position, most promising first. Searching good
+    /// actions first lets alpha-beta prune more.
+    pub fn order(mut self, order: impl FnMut(&S, &mut [S::Action]) + Send + 'static) -> Self {
+        self.order = Some(Box::new(order));
+        self
+    }
+
+    /// Forget every position in the transposition table.
+    pub fn clear(&mut self) {
+        self.table.clear();
+    }
+
+    /// Search `state`, which must be a game in progress.
+    pub fn search(&mut self, state: &S) -> Searched<S::Action> {
+        self.deadline = self.time.map(|time| Instant::now() + time);
+        self.nodes = 0;
+
+        // Every position is searched on this one state by applying and
+        // undoing actions.
+        let mut root = state.clone();
+        let mut best = None;
+        for depth in 1..=self.depth {
+            match self.negamax(&mut root, depth, 0, -WIN, WIN) {
+                Ok(value) => {
+                    let action = self.probe(state).unwrap().action.clone().unwrap();
+                    best = Some((action, value, depth));
+                    // Nothing deeper will change a forced result.
+                    if value.abs() > FORCED {
+                        break;
+                    }
+                }
+                Err(OutOfTime) => break,
+            }
+        }
+
+        let (action, value, depth) = best.unwrap_or_else(|| {
+            // Not even one move deep finished, play the first action.
+            let mut actions = state.legal_actions();
+            self.sort(state, &mut actions);
+            (actions.swap_remove(0), 0, 0)
+        });
+        Searched {
+            action,
+            value,
+            depth,
+            nodes: self.nodes,
+        }
+    }
+
+    /// The actions the searches so far expect both players to play from
+    /// `state`, found by following the best action of each position in the
+    /// table. Stops at the first position the table doesn't know.
+    pub fn line(&self, state: &S) -> Vec<S::Action> {
+        let mut state = state.clone();
+        let mut line = vec![];
+        while line.len() < self.depth {
+            let Some(action) = self.probe(&state).and_then(|entry| entry.action.clone()) else {
+                break;
+            };
+            if state.apply_action(&action).is_err() {
+                break;
+            }
+            line.push(action);
+        }
+        line
+    }
+
+    /// The table entry for `state`, if there is one.
+    fn probe(&self, state: &S) -> Option<&Entry<S::Action>> {
+        let (key, check) = hash(state);
+        self.table.get(&key).filter(|entry| entry.check == check)
+    }
+
+    /// Actions in the order the hook and the table say to try them.
+    fn sort(&mut self, state: &S, actions: &mut [S::Action]) {
+        if let Some(order) = &mut self.order {
+            order(state, actions);
+        }
+        let best = self.probe(state).and_then(|entry| entry.action.as_ref());
+        if let Some(i) = best.and_then(|best| actions.iter().position(|a| a == best)) {
+            actions[..=i].rotate_right(1);
+        }
+    }
+
+    /// Value of `state` for the player to move, searched `depth` moves
+    /// ahead. `ply` is how many moves from the root `state` is.
+    fn negamax(
+        &mut self,
+        state: &mut S,
+        depth: usize,
+        ply: usize,
+        mut alpha: i32,
+        mut beta: i32,
+    ) -> Result<i32, OutOfTime> {
+        self.nodes += 1;
+        // Checking the clock every node would slow the search down.
+        if self.nodes & 1023 == 0 && self.deadline.is_some_and(|d| Instant::now() >= d) {
+            return Err(OutOfTime);
+        }
+        match state.status() {
+            // The player who just moved won.
+            GameStatus::Over { result } if result.winner().is_some() => {
+                return Ok(-(WIN - ply as i32))
+            }
+            GameStatus::Over { .. } => return Ok(0),
+            GameStatus::InProgress { .. } if depth == 0 => return Ok((self.evaluate)(state)),
+            GameStatus::InProgress { .. } => {}
+        }
+
+        let original_alpha = alpha;
+        // The root always searches so it has an action.
+        if let Some(entry) = self.probe(state).filter(|_| ply > 0) {
+            if entry.depth >= depth {
+                let value = from_table(entry.value, ply);
+                match entry.bound {
+                    Bound::Exact => return Ok(value),
+                    Bound::Lower => alpha = alpha.max(value),
+                    Bound::Upper => beta = beta.min(value),
+                }
+                if alpha >= beta {
+                    return Ok(value);
+                }
+            }
+        }
+
+        let mut actions = state.legal_actions();
+        self.sort(state, &mut actions);
+        let mut best = -WIN;
+        let mut best_action = None;
+        for action in actions {
+            state.apply_action(&action).unwrap();
+            let value = self.negamax(state, depth - 1, ply + 1, -beta, -alpha);
+            state.unapply_action(&action).unwrap();
+            let value = -value?;
+            if best_action.is_none() || value > best {
+                best = value;
+                best_action = Some(action);
+            }
+            alpha = alpha.max(value);
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        let bound = if best <= original_alpha {
+            Bound::Upper
+        } else if best >= beta {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+        let (key, check) = hash(state);
+        self.table.insert(
+            key,
+            Entry {
+                check,
+                depth,
+                value: to_table(best, ply),
+                bound,
+                action: best_action,
+            },
+        );
+        Ok(best)
+    }
+}
+
+impl<S, E> Agent<S> for Negamax<S, E>
+where
+    S: GameState,
+    E: FnMut(&S) -> i32,
+{
+    fn choose(&mut self, state: &S) -> S::Action {
+        self.search(state).action
+    }
+}
+
+/// The transposition table key of a state, and a second hash to check the
+/// entry under the key is for the same state.
+fn hash<S: Hash>(state: &S) -> (u64, u64) {
+    let mut hasher = DefaultHasher::new();
+    state.hash(&mut hasher);
+    let key = hasher.finish();
+    // States whose keys collide still hash differently once the hasher has
+    // seen them twice.
+    state.hash(&mut hasher);
+    (key, hasher.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::connect4::{Action, Connect4};
+
+    #[test]
+    fn finds_a_forced_win() {
+        // Blue has 1 and 2 on the bottom row, playing 3 threatens both 0 and
+        // 4 and wins on blue's next move.
+        let state: Connect4 = "2233".parse().unwrap();
+        let searched = Negamax::new(|_: &Connect4| 0, 8).search(&state);
+        assert_eq!(searched.action.column, 3);
+        assert_eq!(searched.value, WIN - 3);
+        // It stops deepening once the win is found.
+        assert_eq!(searched.depth, 3);
+    }
+
+    #[test]
+    fn line_plays_out_the_win() {
+        let state: Connect4 = "2233".parse().unwrap();
+        let mut search = Negamax::new(|_: &Connect4| 0, 8);
+        let searched = search.search(&state);
+        let line = search.line(&state);
+        assert_eq!(line.len(), 3);
+        assert_eq!(line[0], searched.action);
+
+        let mut end = state.clone();
+        for action in &line {
+            end.apply_action(action).unwrap();
+        }
+        assert!(matches!(end.status(), GameStatus::Over { .. }));
+    }
+
+    #[test]
+    fn table_saves_work() {
+        let state: Connect4 = "443".parse().unwrap();
+        let mut search = Negamax::new(|_: &Connect4| 0, 6);
+        let first = search.search(&state);
+        let second = search.search(&state);
+        assert_eq!(first.value, second.value);
+        assert!(second.nodes < first.nodes);
+
+        search.clear();
+        assert_eq!(search.search(&state).nodes, first.nodes);
+    }
+
+    #[test]
+    fn entries_for_other_states_are_ignored() {
+        let state: Connect4 = "2233".parse().unwrap();
+        let mut search = Negamax::new(|_: &Connect4| 0, 8);
+        search.search(&state);
+        assert!(!search.line(&state).is_empty());
+
+        // As if every entry was put there by a state whose key collides.
+        for entry in search.table.values_mut() {
+            entry.check = !entry.check;
+        }
+        assert!(search.line(&state).is_empty());
+    }
+
+    #[test]
+    fn ordering_hook_is_used() {
+        // Every action is as good as every other, so the first one tried is
+        // played.
+        let action = Negamax::new(|_: &Connect4| 0, 2)
+            .order(|_, actions: &mut [Action]| actions.reverse())
+            .choose(&Connect4::default());
+        assert_eq!(action.column, 6);
+    }
+
+    #[test]
+    fn time_limit_stops_deepening() {
+        let mut search = Negamax::new(|_: &Connect4| 0, 42).time_limit(Duration::from_millis(50));
+        let start = Instant::now();
+        let searched = search.search(&Connect4::default());
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert!(searched.depth < 42);
+    }
+}
@@ -0,0 +1,227 @@
+//! Sequential probability ratio test for comparing two players.
+//!
+//! Keep playing games and after each one ask [`Sprt::decide`] whether there
+//! is enough evidence yet. H0 is that the player is `elo0` stronger than its
+//! opponent and H1 that it is `elo1` stronger, usually `elo0` is 0 and `elo1`
+//! a small gain worth detecting. This is the same test chess engine testing
+//! frameworks use, the generalized SPRT on win/tie/loss counts.
+
+use crate::ratings::expected_score;
+
+/// Wins, losses and ties from the tested player's point of view.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Tally {
+    pub wins: usize,
+    pub losses: usize,
+    pub ties: usize,
+}
+
+impl Tally {
+    pub fn games(&self) -> usize {
+        self.wins + self.losses + self.ties
+    }
+
+    /// Frequency of losses, ties and wins (the scores in `SCORES`).
+    /// A tiny count is added to each result so none is ever impossible, a
+    /// player that has won every game so far might still lose one.
+    fn frequencies(&self) -> [f64; 3] {
+        const PRIOR: f64 = 1e-3;
+        let counts = [self.losses, self.ties, self.wins].map(|c| c as f64 + PRIOR);
+        let n: f64 = counts.iter().sum();
+        counts.map(|c| c / n)
+    }
+
+    /// Mean score and variance of the score of a single game.
+    fn score_stats(&self) -> (f64, f64) {
+        let frequencies = self.frequencies();
+        let score: f64 = frequencies.iter().zip(SCORES).map(|(f, x)| f * x).sum();
+        let variance = frequencies
+            .iter()
+            .zip(SCORES)
+            .map(|(f, x)| f * (x - score).powi(2))
+            .sum();
+        (score, variance)
+    }
+
+    /// Estimated Elo difference and the half width of its 95% confidence
+    /// interval. `None` until a game has been played.
+    pub fn elo(&self) -> Option<(f64, f64)> {
+        if self.games() == 0 {
+            return None;
+        }
+        let (score, variance) = self.score_stats();
+        let margin = 1.96 * (variance / self.games() as f64).sqrt();
+        let elo = score_to_elo(score);
+        let low = score_to_elo(score - margin);
+        let high = score_to_elo(score + margin);
+        Some((elo, (high - low) / 2.0))
+    }
+}
+
+/// Score for a loss, a tie and a win.
+const SCORES: [f64; 3] = [0.0, 0.5, 1.0];
+
+/// The most likely distribution of losses, ties and wins given the observed
+/// `frequencies`, out of those with an expected score of `score`.
+///
+/// It has the form `p[i] = frequencies[i] / (1 + lambda * (SCORES[i] - score))`
+/// and `lambda` is found by bisection so the expected score comes out right.
+fn constrained_mle(frequencies: [f64; 3], score: f64) -> [f64; 3] {
+    let mismatch = |lambda: f64| -> f64 {
+        frequencies
+            .iter()
+            .zip(SCORES)
+            .map(|(f, x)| f * (x - score) / (1.0 + lambda * (x - score)))
+            .sum()
+    };
+    // Every denominator has to stay positive.
+    let mut low = -1.0 / (1.0 - score);
+    let mut high = 1.0 / score;
+    for _ in 0..100 {
+        let mid = (low + high) / 2.0;
+        // The mismatch decreases as lambda grows.
+        if mismatch(mid) > 0.0 {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    let lambda = (low + high) / 2.0;
+    let mut p = [0.0; 3];
+    for i in 0..3 {
+        p[i] = frequencies[i] / (1.0 + lambda * (SCORES[i] - score));
+    }
+    p
+}
+
+/// Elo difference that gives an expected score of `score`.
+fn score_to_elo(score: f64) -> f64 {
+    let score = score.clamp(1e-6, 1.0 - 1e-6);
+    -400.0 * (1.0 / score - 1.0).log10()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+    /// Not enough evidence either way, keep playing.
+    Continue,
+    /// The player is not `elo1` stronger.
+    AcceptH0,
+    /// The player is `elo1` stronger.
+    AcceptH1,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sprt {
+    pub elo0: f64,
+    pub elo1: f64,
+    /// Probability of accepting H1 when H0 is true.
+    pub alpha: f64,
+    /// Probability of accepting H0 when H1 is true.
+    pub beta: f64,
+}
+
+impl Sprt {
+    /// Lower and upper bounds on the log likelihood ratio. Crossing the lower
+    /// one accepts H0 and crossing the upper one accepts H1.
+    pub fn bounds(&self) -> (f64, f64) {
+        (
+            (self.beta / (1.0 - self.alpha)).ln(),
+            ((1.0 - self.beta) / self.alpha).ln(),
+        )
+    }
+
+    /// Log likelihood ratio of H1 over H0 given the results so far. Each
+    /// hypothesis is represented by the most likely loss/tie/win distribution
+    /// with its expected score.
+    pub fn llr(&self, tally: &Tally) -> f64 {
+        if tally.games() == 0 {
+            return 0.0;
+        }
+        let frequencies = tally.frequencies();
+        let p0 = constrained_mle(frequencies, expected_score(self.elo0, 0.0));
+        let p1 = constrained_mle(frequencies, expected_score(self.elo1, 0.0));
+        let per_game: f64 = (0..3).map(|i| frequencies[i] * (p1[i] / p0[i]).ln()).sum();
+        tally.games() as f64 * per_game
+    }
+
+    pub fn decide(&self, tally: &Tally) -> Decision {
+        let llr = self.llr(tally);
+        let (lower, upper) = self.bounds();
+        if llr <= lower {
+            Decision::AcceptH0
+        } else if llr >= upper {
+            Decision::AcceptH1
+        } else {
+            Decision::Continue
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPRT: Sprt = Sprt {
+        elo0: 0.0,
+        elo1: 10.0,
+        alpha: 0.05,
+        beta: 0.05,
+    };
+
+    #[test]
+    fn bounds() {
+        let (lower, upper) = SPRT.bounds();
+        assert!((lower + 2.944).abs() < 0.001);
+        assert!((upper - 2.944).abs() < 0.001);
+    }
+
+    #[test]
+    fn decides() {
+        let even = Tally {
+            wins: 100,
+            losses: 100,
+            ties: 0,
+        };
+        assert_eq!(SPRT.decide(&even), Decision::Continue);
+        let even = Tally {
+            wins: 3000,
+            losses: 3000,
+            ties: 1000,
+        };
+        assert_eq!(SPRT.decide(&even), Decision::AcceptH0);
+        let stronger = Tally {
+            wins: 600,
+            losses: 400,
+            ties: 0,
+        };
+        assert_eq!(SPRT.decide(&stronger), Decision::AcceptH1);
+        // A few wins in a row aren't enough to tell 10 Elo apart.
+        let undefeated = Tally {
+            wins: 20,
+            losses: 0,
+            ties: 0,
+        };
+        assert_eq!(SPRT.decide(&undefeated), Decision::Continue);
+        let undefeated = Tally {
+            wins: 200,
+            losses: 0,
+            ties: 0,
+        };
+        assert_eq!(SPRT.decide(&undefeated), Decision::AcceptH1);
+    }
+
+    #[test]
+    fn elo_estimate() {
+        assert_eq!(Tally::default().elo(), None);
+        let (elo, margin) = Tally {
+            wins: 64,
+            losses: 36,
+            ties: 0,
+        }
+        .elo()
+        .unwrap();
+        // A 64% score is about 100 Elo.
+        assert!((elo - 100.0).abs() < 1.0);
+        assert!(margin > 0.0);
+    }
+}